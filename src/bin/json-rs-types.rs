@@ -0,0 +1,55 @@
+//! Prints serde types for sample JSON documents.
+//!
+//! Reads the files given, or standard input if there are none. Each input
+//! may hold several documents one after another; all of them are samples
+//! of the same type.
+
+use json_rs::codegen;
+use json_rs::de::Deserializer;
+use json_rs::infer::Inferrer;
+use std::io::Read;
+
+const USAGE: &str = "usage: json-rs-types [--name NAME] [FILE]...";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("json-rs-types: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut name = "Root".to_string();
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => name = args.next().ok_or(USAGE)?,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let mut inferrer = Inferrer::new();
+    let mut add = |label: &str, input: &str| {
+        inferrer
+            .add_all(Deserializer::new(input).into_stream())
+            .map_err(|err| format!("{}: {}", label, err))
+    };
+    if files.is_empty() {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|err| format!("<stdin>: {}", err))?;
+        add("<stdin>", &input)?;
+    }
+    for file in &files {
+        let input = std::fs::read_to_string(file).map_err(|err| format!("{}: {}", file, err))?;
+        add(file, &input)?;
+    }
+    print!("{}", codegen::generate(&inferrer, &name));
+    Ok(())
+}
@@ -0,0 +1,347 @@
+//! Rust type generation from sample documents.
+//!
+//! The samples' shape is inferred with an [`Inferrer`], then written out as
+//! serde types: objects become structs, values of several types become
+//! untagged enums, members missing from some samples or sometimes `null`
+//! become `Option`s, and integers are `u64`, or `i64` once one is negative.
+//! Keys that are not Rust identifiers get `#[serde(rename)]`, and types with
+//! the same definition are generated once.
+
+use crate::infer::{Inferrer, Object, Shape};
+use crate::tokenizer::Result;
+use std::collections::{HashMap, HashSet};
+
+/// The type for values nothing is known about, such as elements of arrays
+/// that were always empty.
+const ANY: &str = "json_rs::value::Value";
+
+const DERIVE: &str = "#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]";
+
+/// Strict and reserved keywords of every edition.
+const KEYWORDS: [&str; 52] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Type names the generated source can't define without shadowing a type it
+/// uses, or at all.
+const RESERVED_TYPES: [&str; 9] = [
+    "Self",
+    "String",
+    "Vec",
+    "Option",
+    "Box",
+    "Result",
+    "Value",
+    "Serialize",
+    "Deserialize",
+];
+
+/// Rust source for the types of the samples `inferrer` has seen, the
+/// outermost named `root`.
+pub fn generate(inferrer: &Inferrer, root: &str) -> String {
+    let mut generator = Generator {
+        taken: RESERVED_TYPES.iter().map(|name| name.to_string()).collect(),
+        ..Generator::default()
+    };
+    let ty = generator.type_of(&inferrer.root, root);
+    let mut source = String::from("use serde::{Deserialize, Serialize};\n");
+    if !generator.names.values().any(|name| *name == ty) {
+        let alias = unique(type_name(root), &mut generator.taken);
+        source += &format!("\npub type {} = {};\n", alias, ty);
+    }
+    // Types are finished inside out; list the outermost first.
+    for item in generator.items.iter().rev() {
+        source.push('\n');
+        source += item;
+    }
+    source
+}
+
+/// Rust source for the types of the documents in `input`, one after
+/// another.
+pub fn generate_str(input: &str, root: &str) -> Result<String> {
+    let mut inferrer = Inferrer::new();
+    inferrer.add_all(crate::de::Deserializer::new(input).into_stream())?;
+    Ok(generate(&inferrer, root))
+}
+
+#[derive(Default)]
+struct Generator {
+    /// Finished definitions.
+    items: Vec<String>,
+    /// The name of each definition by its body, to reuse it.
+    names: HashMap<String, String>,
+    taken: HashSet<String>,
+}
+
+impl Generator {
+    fn type_of(&mut self, shape: &Shape, hint: &str) -> String {
+        let mut kinds = Vec::new();
+        if shape.boolean {
+            kinds.push(("Bool", "bool".to_string()));
+        }
+        if shape.integer || shape.float {
+            let ty = match (shape.float, shape.negative, shape.huge) {
+                (false, false, _) => "u64",
+                (false, true, false) => "i64",
+                _ => "f64",
+            };
+            kinds.push(("Number", ty.to_string()));
+        }
+        if shape.string.is_some() {
+            kinds.push(("String", "String".to_string()));
+        }
+        if let Some(items) = &shape.items {
+            let element = if items.is_empty() {
+                ANY.to_string()
+            } else {
+                self.type_of(items, &element_name(hint))
+            };
+            kinds.push(("Array", format!("Vec<{}>", element)));
+        }
+        if let Some(object) = &shape.object {
+            kinds.push(("Object", self.structure(object, hint)));
+        }
+        let ty = match kinds.len() {
+            0 => return ANY.to_string(),
+            1 => kinds.remove(0).1,
+            _ => self.union(&kinds, hint),
+        };
+        if shape.null {
+            format!("Option<{}>", ty)
+        } else {
+            ty
+        }
+    }
+
+    fn structure(&mut self, object: &Object, hint: &str) -> String {
+        let mut body = String::new();
+        let mut fields = HashSet::new();
+        for (key, (count, shape)) in &object.members {
+            let field = unique(field_name(key), &mut fields);
+            let mut ty = self.type_of(shape, key);
+            if *count < object.count && !ty.starts_with("Option<") {
+                ty = format!("Option<{}>", ty);
+            }
+            if field.trim_start_matches("r#") != key {
+                body += &format!("    #[serde(rename = {:?})]\n", key);
+            }
+            body += &format!("    pub {}: {},\n", field, ty);
+        }
+        self.define("struct", &body, hint, |name| {
+            format!("{}\npub struct {} {{\n{}}}\n", DERIVE, name, body)
+        })
+    }
+
+    fn union(&mut self, kinds: &[(&str, String)], hint: &str) -> String {
+        let body: String = kinds
+            .iter()
+            .map(|(variant, ty)| format!("    {}({}),\n", variant, ty))
+            .collect();
+        self.define("enum", &body, &format!("{} value", hint), |name| {
+            format!(
+                "{}\n#[serde(untagged)]\npub enum {} {{\n{}}}\n",
+                DERIVE, name, body
+            )
+        })
+    }
+
+    /// The name of the `kind` with `body`, defining it with `item` unless
+    /// an identical one exists.
+    fn define(
+        &mut self,
+        kind: &str,
+        body: &str,
+        hint: &str,
+        item: impl FnOnce(&str) -> String,
+    ) -> String {
+        let key = format!("{} {}", kind, body);
+        if let Some(name) = self.names.get(&key) {
+            return name.clone();
+        }
+        let name = unique(type_name(hint), &mut self.taken);
+        self.items.push(item(&name));
+        self.names.insert(key, name.clone());
+        name
+    }
+}
+
+/// `name`, or `name` with a number appended if it is taken.
+fn unique(name: String, taken: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while !taken.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{}{}", name, n);
+    }
+    candidate
+}
+
+/// The ASCII words of `key`, split at separators and case changes:
+/// `userID_v2` is `user`, `ID`, `v2`.
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if !ch.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let after_lower =
+            i > 0 && (chars[i - 1].is_ascii_lowercase() || chars[i - 1].is_ascii_digit());
+        let before_lower = chars.get(i + 1).is_some_and(char::is_ascii_lowercase);
+        if ch.is_ascii_uppercase() && !word.is_empty() && (after_lower || before_lower) {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(ch);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn type_name(key: &str) -> String {
+    let mut name: String = words(key)
+        .iter()
+        .map(|word| {
+            let word = word.to_ascii_lowercase();
+            word[..1].to_ascii_uppercase() + &word[1..]
+        })
+        .collect();
+    if name.is_empty() {
+        name = "Value".to_string();
+    }
+    if name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        name.insert(0, 'T');
+    }
+    name
+}
+
+fn field_name(key: &str) -> String {
+    let words: Vec<String> = words(key).iter().map(|w| w.to_ascii_lowercase()).collect();
+    let mut name = words.join("_");
+    if name.is_empty() {
+        name = "field".to_string();
+    }
+    if name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    match name.as_str() {
+        // Not allowed as raw identifiers.
+        "self" | "super" | "crate" => name + "_",
+        _ if KEYWORDS.contains(&name.as_str()) => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+/// The name for the elements of an array named `name`: its singular if it
+/// looks like a plural, else `name` with `Item` appended.
+fn element_name(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+    for suffix in ["sses", "xes", "ches", "shes"] {
+        if let Some(stem) = name.strip_suffix(suffix) {
+            return format!("{}{}", stem, &suffix[..suffix.len() - 2]);
+        }
+    }
+    match name.strip_suffix('s') {
+        Some(singular) if singular.len() > 1 && !singular.ends_with('s') => singular.to_string(),
+        _ => format!("{} item", name),
+    }
+}
+
+#[test]
+fn test_codegen() {
+    let samples = r#"
+        {"id": 1, "userName": "a", "type": "x", "2fa": true, "score": 1, "tags": ["a"],
+         "home": {"city": "x", "zip": "1"}, "work": {"city": "y", "zip": "2"},
+         "orders": [{"id": 5, "total": 1.5, "items": []}], "self": null, "@meta": 1}
+        {"id": 2, "userName": "b", "type": "y", "2fa": false, "score": -3, "tags": [],
+         "home": {"city": "z", "zip": "3"}, "work": null,
+         "orders": [], "self": "me", "@meta": "one"}
+    "#;
+    let source = generate_str(samples, "user").unwrap();
+    assert_eq!(
+        source,
+        r#"use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    #[serde(rename = "2fa")]
+    pub _2fa: bool,
+    #[serde(rename = "@meta")]
+    pub meta: MetaValue,
+    pub home: Home,
+    pub id: u64,
+    pub orders: Vec<Order>,
+    pub score: i64,
+    #[serde(rename = "self")]
+    pub self_: Option<String>,
+    pub tags: Vec<String>,
+    pub r#type: String,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    pub work: Option<Home>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub id: u64,
+    pub items: Vec<json_rs::value::Value>,
+    pub total: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Home {
+    pub city: String,
+    pub zip: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetaValue {
+    Number(u64),
+    String(String),
+}
+"#
+    );
+
+    assert_eq!(
+        generate_str("[1, 2.5] [3]", "root").unwrap(),
+        "use serde::{Deserialize, Serialize};\n\npub type Root = Vec<f64>;\n"
+    );
+    // Names that would shadow a type the source uses, or are keywords.
+    let source = generate_str(
+        r#"{"string": {"a": 1}, "vec": {"b": 1}, "self": {"c": 1}, "try": 1, "gen": 2}"#,
+        "root",
+    )
+    .unwrap();
+    for line in [
+        "pub struct String2 {",
+        "pub struct Vec2 {",
+        "pub struct Self2 {",
+        "    pub string: String2,",
+        "    pub r#try: u64,",
+        "    pub r#gen: u64,",
+    ] {
+        assert!(source.contains(line), "{line} in {source}");
+    }
+    assert_eq!(
+        generate_str(r#"["a"]"#, "string").unwrap(),
+        "use serde::{Deserialize, Serialize};\n\npub type String2 = Vec<String>;\n"
+    );
+    assert_eq!(field_name("HTTPServerID"), "http_server_id");
+    assert_eq!(type_name("ip_v4Address"), "IpV4Address");
+    assert_eq!(element_name("addresses"), "address");
+    assert_eq!(element_name("categories"), "category");
+    assert_eq!(element_name("data"), "data item");
+}
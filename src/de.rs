@@ -1,44 +1,314 @@
 use crate::error::Error;
-use crate::token::{MaybeString, ParseNumber, Token};
+use crate::index::StructuralIndex;
+use crate::intern::{self, Interned, KeyInterner};
+use crate::token::{MaybeString, ParseNumber, Punct, Token};
 use crate::tokenizer::Result;
 use crate::tokenizer::Tokenizer;
-use serde::{de, Deserialize};
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, IntoDeserializer};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// What to do with an object key that occurs more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Hand every member to the target type. Maps and `Value` keep the last
+    /// one; derived structs fail on the repeated field.
+    #[default]
+    Allow,
+    /// Fail with `Error::DuplicateKey`, giving where both occurrences are.
+    Error,
+    /// Keep the first member with the key and skip the others.
+    FirstWins,
+    /// Keep the last member with the key and skip the others. The first
+    /// object met is read ahead once, to its end, to find where each key
+    /// last occurs in it and in every object inside it; only repeated keys
+    /// are remembered.
+    LastWins,
+}
 
 pub struct Deserializer<'a> {
-    input: &'a str,
     tokenizer: Tokenizer<'a>,
+    /// One token of lookahead, so a peeked token is not lexed again.
+    peeked: Option<Token<'a>>,
+    /// Lets ignored objects and arrays be skipped in one step.
+    index: Option<StructuralIndex>,
+    /// The first structural in `index` not yet passed.
+    cursor: usize,
+    /// Shares the text of repeated `intern::Key` map keys and escaped keys.
+    interner: Option<KeyInterner>,
+    duplicate_keys: DuplicateKeys,
+    /// With `DuplicateKeys::LastWins`, where each repeated key last occurs,
+    /// by the position just past the `{` of its object.
+    last_keys: HashMap<usize, HashMap<String, usize>>,
+    /// The end of the input read ahead for `last_keys`.
+    scanned_to: usize,
 }
 
 impl<'a> Deserializer<'a> {
     pub fn new(s: &'a str) -> Self {
         Deserializer {
-            input: s,
             tokenizer: Tokenizer::new(s),
+            peeked: None,
+            index: None,
+            cursor: 0,
+            interner: None,
+            duplicate_keys: DuplicateKeys::default(),
+            last_keys: HashMap::new(),
+            scanned_to: 0,
+        }
+    }
+
+    /// Builds a structural index of `s` first, so that ignored values are
+    /// skipped without being lexed. Worth it for large documents of which
+    /// only a few fields are read.
+    pub fn with_structural_index(s: &'a str) -> Result<Self> {
+        let mut de = Deserializer::new(s);
+        de.index = Some(StructuralIndex::new(s)?);
+        Ok(de)
+    }
+
+    /// Deserializes from a buffer that escaped strings are unescaped into,
+    /// so `&'a str` fields borrow every string, escaped or not, and no
+    /// string is allocated. The contents of `s` are unspecified afterwards,
+    /// but it stays valid UTF-8.
+    pub fn from_mut_str(s: &'a mut str) -> Self {
+        Deserializer {
+            tokenizer: Tokenizer::from_mut_str(s),
+            peeked: None,
+            index: None,
+            cursor: 0,
+            interner: None,
+            duplicate_keys: DuplicateKeys::default(),
+            last_keys: HashMap::new(),
+            scanned_to: 0,
+        }
+    }
+
+    /// Deserializes the value at byte `pos` of `s`; errors still report
+    /// positions in the whole of `s`.
+    pub(crate) fn starting_at(s: &'a str, pos: usize) -> Self {
+        let mut de = Deserializer::new(s);
+        de.tokenizer.set_pos(pos);
+        de
+    }
+
+    /// Interns map keys deserialized as [`intern::Key`] in `interner`, for
+    /// every document read through this deserializer. Escaped keys, struct
+    /// field names included, are looked up by their raw text and only
+    /// decoded the first time.
+    pub fn key_interner(mut self, interner: KeyInterner) -> Self {
+        self.interner = Some(interner);
+        self
+    }
+
+    /// Sets how objects with a repeated key are read, for structs, maps and
+    /// `Value` alike.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Gives back the interner, to carry it over to another deserializer.
+    pub fn into_interner(self) -> Option<KeyInterner> {
+        self.interner
+    }
+
+    /// Reads a sequence of values separated by whitespace, such as JSON
+    /// Lines, as `T`s.
+    pub fn into_stream<T>(self) -> StreamDeserializer<'a, T>
+    where
+        T: de::Deserialize<'a>,
+    {
+        StreamDeserializer {
+            de: self,
+            failed: false,
+            marker: PhantomData,
         }
     }
+
+    /// Reads an array of numbers as `f64`s in one loop, with none of the
+    /// per-element seed and visitor calls a `Vec<f64>` goes through.
+    pub fn read_f64_array(&mut self) -> Result<Vec<f64>> {
+        self.read_number_array("f64", ParseNumber::to_f64)
+    }
+
+    /// Reads an array of integers as `i64`s in one loop, failing like a
+    /// `Vec<i64>` would on fractions and integers out of range.
+    pub fn read_i64_array(&mut self) -> Result<Vec<i64>> {
+        self.read_number_array("i64", |number| match number {
+            ParseNumber::U64(n) => i64::try_from(n)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(n), &"i64")),
+            ParseNumber::I64(n) => Ok(n),
+            ParseNumber::Float(f) => Err(de::Error::invalid_type(
+                de::Unexpected::Float(f.to_f64()?),
+                &"i64",
+            )),
+        })
+    }
+
+    fn read_number_array<T>(
+        &mut self,
+        expected: &'static str,
+        convert: fn(ParseNumber<'a>) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.expect(Punct::LeftBracket)?;
+        let mut out = Vec::new();
+        let mut first = true;
+        while self.tokenizer.element_start(first)? {
+            first = false;
+            match self.tokenizer.number()? {
+                Some(number) => out.push(convert(number)?),
+                None => {
+                    let token = self.tokenizer.next()?;
+                    let unexpected = match &token {
+                        Token::Bool(b, _) => de::Unexpected::Bool(*b),
+                        Token::Null(_) => de::Unexpected::Unit,
+                        Token::String(s, _) => de::Unexpected::Str(s.as_str()),
+                        Token::Punct(Punct::LeftBracket, _) => de::Unexpected::Seq,
+                        Token::Punct(Punct::LeftCurly, _) => de::Unexpected::Map,
+                        Token::Punct(punct, (at, _)) => {
+                            return Err(Error::Unexpected(*at, *punct as u8 as char))
+                        }
+                        Token::Eof => {
+                            return Err(Error::EofWhileParsingValue(self.tokenizer.pos()))
+                        }
+                        Token::Number(..) => de::Unexpected::Other("number"),
+                    };
+                    return Err(de::Error::invalid_type(unexpected, &expected));
+                }
+            }
+        }
+        Ok(out)
+    }
 }
 
 impl<'a> Deserializer<'a> {
     fn next(&mut self) -> Result<Token<'a>> {
-        self.tokenizer.next()
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.tokenizer.next(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<&Token<'a>> {
+        let token = self.next()?;
+        Ok(self.peeked.insert(token))
+    }
+
+    /// Skips the object or array that comes next using the structural
+    /// index. Returns false if there is no index or no container to skip.
+    fn skip_indexed(&mut self) -> Result<bool> {
+        if self.index.is_none() {
+            return Ok(false);
+        }
+        let pos = match self.peek()? {
+            Token::Punct(Punct::LeftCurly | Punct::LeftBracket, (pos, _)) => *pos,
+            _ => return Ok(false),
+        };
+        let index = match &self.index {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+        let open = index.seek(self.cursor, pos);
+        let close = match index.partner(open) {
+            Some(close) => close,
+            None => return Ok(false),
+        };
+        self.cursor = close + 1;
+        self.peeked = None;
+        self.tokenizer.set_pos(index.position(close) + 1);
+        Ok(true)
+    }
+
+    /// Where each repeated key of the object being read last occurs. The
+    /// first time, reads ahead from just past its `{` to its end, recording
+    /// the keys of the objects inside it too, so that each byte is read
+    /// ahead at most once. Stops at anything malformed, which the real pass
+    /// reports.
+    fn last_keys(&mut self) -> HashMap<String, usize> {
+        let start = self.tokenizer.pos();
+        if start >= self.scanned_to {
+            self.scan_last_keys();
+        }
+        self.last_keys.remove(&start).unwrap_or_default()
     }
 
-    fn peek(&self) -> Result<Token> {
-        self.tokenizer.peek()
+    fn scan_last_keys(&mut self) {
+        /// The objects and arrays open, with the keys seen in each object
+        /// and whether they were repeated.
+        type Open = Vec<Option<(usize, HashMap<String, (usize, bool)>)>>;
+        fn close(last_keys: &mut HashMap<usize, HashMap<String, usize>>, open: &mut Open) {
+            if let Some(Some((start, keys))) = open.pop() {
+                let repeated: HashMap<_, _> = keys
+                    .into_iter()
+                    .filter(|(_, (_, repeated))| *repeated)
+                    .map(|(key, (at, _))| (key, at))
+                    .collect();
+                if !repeated.is_empty() {
+                    last_keys.insert(start, repeated);
+                }
+            }
+        }
+
+        let mut t = self.tokenizer.lookahead();
+        let mut open: Open = vec![Some((t.pos(), HashMap::new()))];
+        let mut key_next = true;
+        while !open.is_empty() {
+            let token = match t.next() {
+                Ok(token) => token,
+                Err(_) => break,
+            };
+            match token {
+                Token::String(key, (at, _)) if key_next => {
+                    if let Some(Some((_, keys))) = open.last_mut() {
+                        keys.entry(key.as_str().to_string())
+                            .and_modify(|(last, repeated)| (*last, *repeated) = (at, true))
+                            .or_insert((at, false));
+                    }
+                    if t.expect(Punct::Colon).is_err() {
+                        break;
+                    }
+                    key_next = false;
+                }
+                Token::Punct(Punct::LeftCurly, _) => {
+                    open.push(Some((t.pos(), HashMap::new())));
+                    key_next = true;
+                }
+                Token::Punct(Punct::LeftBracket, _) => {
+                    open.push(None);
+                    key_next = false;
+                }
+                Token::Punct(Punct::RightCurly | Punct::RightBracket, _) => {
+                    close(&mut self.last_keys, &mut open);
+                    key_next = false;
+                }
+                Token::Punct(Punct::Comma, _) => key_next = matches!(open.last(), Some(Some(_))),
+                _ => key_next = false,
+            }
+        }
+        while !open.is_empty() {
+            close(&mut self.last_keys, &mut open);
+        }
+        self.scanned_to = t.pos();
     }
 
-    pub fn expect(&mut self, op: String) -> Result<()> {
-        self.tokenizer.expect(op)
+    pub fn expect(&mut self, punct: Punct) -> Result<()> {
+        match self.peeked.take() {
+            Some(Token::Punct(p, _)) if p == punct => Ok(()),
+            Some(token) => Err(self.tokenizer.unexpected(&token, punct)),
+            None => self.tokenizer.expect(punct),
+        }
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
-    type Error = Error;
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+impl<'de> Deserializer<'de> {
+    fn visit_token<V>(&mut self, token: Token<'de>, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.next()? {
+        match token {
             Token::Bool(val, _) => visitor.visit_bool(val),
             Token::Null(_) => visitor.visit_unit(),
             Token::Number(val, _) => val.visit(visitor),
@@ -56,23 +326,75 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             }
         }
     }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let token = self.next()?;
+        self.visit_token(token, visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next()? {
+            Token::Number(val, _) => visitor.visit_f32(val.to_f32()?),
+            token => self.visit_token(token, visitor),
+        }
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.next()? {
+            Token::Number(val, _) => visitor.visit_f64(val.to_f64()?),
+            token => self.visit_token(token, visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if !self.skip_indexed()? {
+            match self.peeked.take() {
+                Some(Token::Punct(open @ (Punct::LeftCurly | Punct::LeftBracket), _)) => {
+                    self.tokenizer.skip_nested(open)?
+                }
+                Some(Token::Punct(punct, (at, _))) => {
+                    return Err(Error::Unexpected(at, punct as u8 as char))
+                }
+                Some(Token::Eof) => return Err(Error::EofWhileParsingValue(self.tokenizer.pos())),
+                Some(_) => {}
+                None => self.tokenizer.skip_value()?,
+            }
+        }
+        visitor.visit_unit()
+    }
 
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 i128 char str string seq
+        bool u8 u16 u32 u64 i8 i16 i32 i64 i128 char str string seq
         bytes byte_buf map unit newtype_struct
-        ignored_any unit_struct tuple_struct tuple option identifier
+        unit_struct tuple_struct tuple option identifier
     }
 
     fn deserialize_struct<V>(
         self,
-        name: &'static str,
-        fields: &'static [&'static str],
+        _name: &'static str,
+        _fields: &'static [&'static str],
         visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        if self.peek()?.is_left_curly() || self.peek()?.is_left_bracket() {
+        let token = self.peek()?;
+        if token.is_left_curly() || token.is_left_bracket() {
             return self.deserialize_any(visitor);
         }
         Err(Error::InvalidStructString())
@@ -80,8 +402,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_enum<V>(
         self,
-        name: &'static str,
-        variants: &'static [&'static str],
+        _name: &'static str,
+        _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
@@ -97,13 +419,47 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 struct MapAccess<'de, 'a> {
     de: &'a mut Deserializer<'de>,
     first: bool,
+    /// Where each key was first seen, or with `DuplicateKeys::LastWins`,
+    /// where each repeated key is last seen.
+    keys: HashMap<String, usize>,
 }
 
 impl<'de, 'a> MapAccess<'de, 'a> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
+        let keys = match de.duplicate_keys {
+            DuplicateKeys::LastWins => de.last_keys(),
+            _ => HashMap::new(),
+        };
         MapAccess {
-            de: de,
+            de,
             first: true,
+            keys,
+        }
+    }
+
+    /// Whether the member whose key comes next is the one to keep.
+    fn keep_member(&mut self) -> Result<bool> {
+        let policy = self.de.duplicate_keys;
+        if policy == DuplicateKeys::Allow {
+            return Ok(true);
+        }
+        let (key, at) = match self.de.peek()? {
+            Token::String(key, (at, _)) => (key.as_str(), *at),
+            _ => return Err(Error::JSONKeyMustBeString()),
+        };
+        match (policy, self.keys.get(key)) {
+            (DuplicateKeys::Error, Some(&first)) => Err(Error::DuplicateKey {
+                key: key.to_string(),
+                first,
+                second: at,
+            }),
+            (DuplicateKeys::LastWins, Some(&last)) => Ok(last == at),
+            (DuplicateKeys::LastWins, None) => Ok(true),
+            (_, Some(_)) => Ok(false),
+            (_, None) => {
+                self.keys.insert(key.to_string(), at);
+                Ok(true)
+            }
         }
     }
 }
@@ -115,23 +471,37 @@ impl<'de, 'a> de::MapAccess<'de> for MapAccess<'de, 'a> {
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.de.peek()?.is_right_curly() {
+        loop {
+            // The key itself is not lexed here, so that an escaped one can be
+            // looked up in the interner before it is decoded.
+            let close = match &self.de.peeked {
+                Some(token) => token.is_right_curly(),
+                None => self.de.tokenizer.peek_byte() == Some(b'}'),
+            };
+            if close {
+                self.de.next()?;
+                return Ok(None);
+            }
+            if self.first {
+                self.first = false
+            } else {
+                self.de.expect(Punct::Comma)?;
+            }
+            if self.keep_member()? {
+                return seed.deserialize(MapKey::new(&mut *self.de)).map(Some);
+            }
+            // A duplicate to drop: skip its key and value.
             self.de.next()?;
-            return Ok(None);
-        }
-        if self.first {
-            self.first = false
-        } else {
-            self.de.expect(",".to_string())?;
+            self.de.expect(Punct::Colon)?;
+            de::Deserializer::deserialize_ignored_any(&mut *self.de, de::IgnoredAny)?;
         }
-        seed.deserialize(MapKey::new(&mut *self.de)).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        self.de.expect(":".to_string())?;
+        self.de.expect(Punct::Colon)?;
         seed.deserialize(&mut *self.de)
     }
 }
@@ -143,10 +513,7 @@ struct SeqAccess<'de, 'a> {
 
 impl<'de, 'a> SeqAccess<'de, 'a> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
-        SeqAccess {
-            de: de,
-            first: true,
-        }
+        SeqAccess { de, first: true }
     }
 }
 
@@ -157,6 +524,18 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'de, 'a> {
     where
         T: de::DeserializeSeed<'de>,
     {
+        if self.de.peeked.is_none() {
+            // Arrays of numbers are read straight off the bytes, with no
+            // token built or buffered for the separators and elements.
+            if !self.de.tokenizer.element_start(self.first)? {
+                return Ok(None);
+            }
+            self.first = false;
+            if let Some(number) = self.de.tokenizer.number()? {
+                return Ok(Some(seed.deserialize(NumberDeserializer(number))?));
+            }
+            return Ok(Some(seed.deserialize(&mut *self.de)?));
+        }
         if self.de.peek()?.is_right_bracket() {
             self.de.next()?;
             return Ok(None);
@@ -164,19 +543,76 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'de, 'a> {
         if self.first {
             self.first = false;
         } else {
-            self.de.expect(",".to_string())?;
+            self.de.expect(Punct::Comma)?;
         }
         Ok(Some(seed.deserialize(&mut *self.de)?))
     }
 }
 
+/// Deserializes an array element already lexed as a number.
+struct NumberDeserializer<'a>(ParseNumber<'a>);
+
+impl<'de> de::Deserializer<'de> for NumberDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.0.visit(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_f32(self.0.to_f32()?)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_f64(self.0.to_f64()?)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 i128 u128 char str string seq
+        bytes byte_buf map unit newtype_struct unit_struct tuple_struct
+        tuple option identifier struct enum ignored_any
+    }
+}
+
 struct MapKey<'de, 'a> {
     de: &'a mut Deserializer<'de>,
 }
 
 impl<'de, 'a> MapKey<'de, 'a> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
-        MapKey { de: de }
+        MapKey { de }
+    }
+
+    /// With an interner, the shared copy of the next key if it has escapes,
+    /// found by its raw text so that a key seen before is not decoded again.
+    fn escaped_key(&mut self) -> Result<Option<Arc<str>>> {
+        let de = &mut *self.de;
+        let interner = match &mut de.interner {
+            Some(interner) if de.peeked.is_none() => interner,
+            _ => return Ok(None),
+        };
+        let (raw, end) = match de.tokenizer.escaped_string() {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        if let Some(key) = interner.get_escaped(raw) {
+            de.tokenizer.set_pos(end);
+            return Ok(Some(key));
+        }
+        let raw = raw.to_owned();
+        match de.tokenizer.next()? {
+            Token::String(key, _) => Ok(Some(interner.intern_escaped(&raw, key.as_str()))),
+            _ => Err(Error::JSONKeyMustBeString()),
+        }
     }
 }
 
@@ -195,16 +631,19 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'de, 'a> {
         }
     }
 
-    deserialize_integer_key!(deserialize_i8 => visit_i8);
-    deserialize_integer_key!(deserialize_i16 => visit_i16);
-    deserialize_integer_key!(deserialize_i32 => visit_i32);
-    deserialize_integer_key!(deserialize_i64 => visit_i64);
-    deserialize_integer_key!(deserialize_i128 => visit_i128);
-    deserialize_integer_key!(deserialize_u8 => visit_u8);
-    deserialize_integer_key!(deserialize_u16 => visit_u16);
-    deserialize_integer_key!(deserialize_u32 => visit_u32);
-    deserialize_integer_key!(deserialize_u64 => visit_u64);
-    deserialize_integer_key!(deserialize_u128 => visit_u128);
+    deserialize_parsed_key!(deserialize_i8 => visit_i8);
+    deserialize_parsed_key!(deserialize_i16 => visit_i16);
+    deserialize_parsed_key!(deserialize_i32 => visit_i32);
+    deserialize_parsed_key!(deserialize_i64 => visit_i64);
+    deserialize_parsed_key!(deserialize_i128 => visit_i128);
+    deserialize_parsed_key!(deserialize_u8 => visit_u8);
+    deserialize_parsed_key!(deserialize_u16 => visit_u16);
+    deserialize_parsed_key!(deserialize_u32 => visit_u32);
+    deserialize_parsed_key!(deserialize_u64 => visit_u64);
+    deserialize_parsed_key!(deserialize_u128 => visit_u128);
+    deserialize_parsed_key!(deserialize_f32 => visit_f32, |s| number_key(s)?.to_f32().ok());
+    deserialize_parsed_key!(deserialize_f64 => visit_f64, |s| number_key(s)?.to_f64().ok());
+    deserialize_parsed_key!(deserialize_bool => visit_bool);
 
     #[inline]
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
@@ -216,32 +655,54 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'de, 'a> {
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(mut self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        #[cfg(feature = "raw_value")]
-        {
-            if name == crate::raw::TOKEN {
-                return self.de.deserialize_raw_value(visitor);
-            }
+        if name != intern::TOKEN || self.de.interner.is_none() {
+            return visitor.visit_newtype_struct(self);
+        }
+        if let Some(key) = self.escaped_key()? {
+            return visitor.visit_newtype_struct(Interned(key));
         }
+        let key = match self.de.next()? {
+            Token::String(s, _) => s,
+            _ => return Err(Error::JSONKeyMustBeString()),
+        };
+        match &mut self.de.interner {
+            Some(interner) => visitor.visit_newtype_struct(Interned(interner.intern(key.as_str()))),
+            None => visitor.visit_str(key.as_str()),
+        }
+    }
 
-        let _ = name;
-        visitor.visit_newtype_struct(self)
+    fn deserialize_identifier<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.escaped_key()? {
+            Some(key) => visitor.visit_str(&key),
+            None => self.deserialize_any(visitor),
+        }
     }
 
     #[inline]
     fn deserialize_enum<V>(
         self,
-        name: &'static str,
-        variants: &'static [&'static str],
+        _name: &'static str,
+        _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.de.deserialize_enum(name, variants, visitor)
+        // Only unit variants can be spelled as a JSON object key.
+        match self.de.next()? {
+            Token::String(s, _) => match s {
+                MaybeString::Escaped(s) => visitor.visit_enum(s.into_deserializer()),
+                MaybeString::NotEscaped(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+            },
+            _ => Err(Error::JSONKeyMustBeString()),
+        }
     }
 
     #[inline]
@@ -261,8 +722,8 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'de, 'a> {
     }
 
     serde::forward_to_deserialize_any! {
-        bool f32 f64 char str string unit unit_struct seq tuple tuple_struct map
-        struct identifier ignored_any
+        char str string unit unit_struct seq tuple tuple_struct map struct
+        ignored_any
     }
 }
 
@@ -272,7 +733,7 @@ struct VariantAccess<'de, 'a> {
 
 impl<'de, 'a> VariantAccess<'de, 'a> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
-        VariantAccess { de: de }
+        VariantAccess { de }
     }
 }
 
@@ -286,7 +747,7 @@ impl<'de, 'a> de::EnumAccess<'de> for VariantAccess<'de, 'a> {
         V: de::DeserializeSeed<'de>,
     {
         let val = seed.deserialize(&mut *self.de)?;
-        self.de.expect(":".to_string())?;
+        self.de.expect(Punct::Colon)?;
         Ok((val, self))
     }
 }
@@ -305,7 +766,7 @@ impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'de, 'a> {
         seed.deserialize(self.de)
     }
 
-    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
@@ -320,42 +781,147 @@ impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'de, 'a> {
     }
 }
 
-macro_rules! deserialize_integer_key {
+/// Object keys are always strings; keys of non-string types are parsed out
+/// of the string, falling back to the raw string so the visitor can report
+/// the type mismatch.
+macro_rules! deserialize_parsed_key {
     ($method:ident => $visit:ident) => {
+        deserialize_parsed_key!($method => $visit, |s: &str| s.parse().ok());
+    };
+    ($method:ident => $visit:ident, $parse:expr) => {
         fn $method<V>(self, visitor: V) -> Result<V::Value>
         where
             V: de::Visitor<'de>,
         {
-            let token = self.de.next()?;
-            let tmp: String;
-            match token {
-                Token::String(s, _) => match s {
-                    MaybeString::Escaped(s) => tmp = s,
-                    MaybeString::NotEscaped(s) => tmp = s.to_string(),
-                },
+            let s = match self.de.next()? {
+                Token::String(s, _) => s,
                 _ => return Err(Error::JSONKeyMustBeString()),
-            }
-            match (tmp.parse(), tmp) {
-                (Ok(integer), _) => visitor.$visit(integer),
-                (Err(_), s) => visitor.visit_string(s),
+            };
+            let parsed = $parse(s.as_str());
+            match (parsed, s) {
+                (Some(v), _) => visitor.$visit(v),
+                (None, MaybeString::Escaped(s)) => visitor.visit_string(s),
+                (None, MaybeString::NotEscaped(s)) => visitor.visit_borrowed_str(s),
             }
         }
     };
 }
 
-use deserialize_integer_key;
+use deserialize_parsed_key;
+
+/// `s` as a number if it is one JSON number and nothing else, so float keys
+/// take what a JSON writer emits but not `NaN`, `inf` or `+1`.
+fn number_key(s: &str) -> Option<ParseNumber<'_>> {
+    if !s.starts_with(|ch: char| ch == '-' || ch.is_ascii_digit()) {
+        return None;
+    }
+    match crate::float::scan(s) {
+        Ok((number, len)) if len == s.len() => Some(number),
+        _ => None,
+    }
+}
+
+pub fn from_str<'a, T>(input: &'a str) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_str_with(input, DuplicateKeys::Allow)
+}
 
-fn from_str<'a, T>(input: &'a str) -> Result<T>
+/// Like [`from_str`], reading objects with a repeated key by `policy`.
+pub fn from_str_with<'a, T>(input: &'a str, policy: DuplicateKeys) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::new(input);
+    let mut deserializer = Deserializer::new(input).duplicate_keys(policy);
     let value: T = de::Deserialize::deserialize(&mut deserializer)?;
     Ok(value)
 }
 
+/// Iterator over the whitespace-separated values of one input, made by
+/// [`Deserializer::into_stream`]. Stops after the first error.
+pub struct StreamDeserializer<'a, T> {
+    de: Deserializer<'a>,
+    failed: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> StreamDeserializer<'a, T> {
+    /// The byte offset just past the last value read.
+    pub fn byte_offset(&self) -> usize {
+        match self.de.peeked.as_ref().and_then(Token::span) {
+            Some((start, _)) => start,
+            None => self.de.tokenizer.pos(),
+        }
+    }
+
+    pub fn into_deserializer(self) -> Deserializer<'a> {
+        self.de
+    }
+}
+
+impl<'a, T> Iterator for StreamDeserializer<'a, T>
+where
+    T: de::Deserialize<'a>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.failed {
+            return None;
+        }
+        let result = match self.de.peek() {
+            Ok(Token::Eof) => return None,
+            Ok(_) => T::deserialize(&mut self.de),
+            Err(e) => Err(e),
+        };
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+/// Like [`from_str`], but unescapes strings in place in `input`; see
+/// [`Deserializer::from_mut_str`].
+pub fn from_mut_str<'a, T>(input: &'a mut str) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_mut_str_with(input, DuplicateKeys::Allow)
+}
+
+/// Like [`from_mut_str`], reading objects with a repeated key by `policy`.
+pub fn from_mut_str_with<'a, T>(input: &'a mut str, policy: DuplicateKeys) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_mut_str(input).duplicate_keys(policy);
+    let value: T = de::Deserialize::deserialize(&mut deserializer)?;
+    Ok(value)
+}
+
+/// Like [`from_mut_str`], for input that has not been checked to be UTF-8.
+pub fn from_mut_slice<'a, T>(input: &'a mut [u8]) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_mut_slice_with(input, DuplicateKeys::Allow)
+}
+
+/// Like [`from_mut_slice`], reading objects with a repeated key by `policy`.
+pub fn from_mut_slice_with<'a, T>(input: &'a mut [u8], policy: DuplicateKeys) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    match std::str::from_utf8_mut(input) {
+        Ok(s) => from_mut_str_with(s, policy),
+        Err(e) => Err(Error::InvalidUtf8(e.valid_up_to())),
+    }
+}
+
 #[test]
 fn test() {
+    use serde::Deserialize;
+
     #[derive(Deserialize, Debug)]
     enum User2<'a> {
         Test {
@@ -364,6 +930,7 @@ fn test() {
             age: i128,
         },
     }
+    #[allow(dead_code)]
     #[derive(Deserialize, Debug)]
     struct User<'a> {
         fingerprint: &'a str,
@@ -389,3 +956,367 @@ fn test() {
         Err(e) => println!("{}", e),
     }
 }
+
+#[test]
+fn test_map_key() {
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Deserialize, Debug, PartialEq, Eq, Hash)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct Id(u32);
+
+    let colors: HashMap<Color, u32> = from_str(r#"{"Red": 1, "Green": 2}"#).unwrap();
+    assert_eq!(colors[&Color::Red], 1);
+    assert_eq!(colors[&Color::Green], 2);
+
+    let bools: BTreeMap<bool, u32> = from_str(r#"{"true": 1, "false": 0}"#).unwrap();
+    assert_eq!(bools, [(false, 0), (true, 1)].into_iter().collect());
+
+    let chars: BTreeMap<char, u32> = from_str(r#"{"a": 1}"#).unwrap();
+    assert_eq!(chars, [('a', 1)].into_iter().collect());
+
+    let ids: BTreeMap<Id, u32> = from_str(r#"{"7": 1}"#).unwrap();
+    assert_eq!(ids, [(Id(7), 1)].into_iter().collect());
+
+    let float = f64::deserialize(MapKey::new(&mut Deserializer::new(r#""1.5""#))).unwrap();
+    assert_eq!(float, 1.5);
+
+    let float_key = |s: &str| f64::deserialize(MapKey::new(&mut Deserializer::new(s)));
+    assert_eq!(float_key(r#""-0.5""#), Ok(-0.5));
+    assert_eq!(float_key(r#""2e3""#), Ok(2000.0));
+    for key in ["NaN", "inf", "+1.5", "1.", " 1", "1x"] {
+        assert!(float_key(&format!("{:?}", key)).is_err(), "{}", key);
+    }
+
+    assert!(from_str::<HashMap<Color, u32>>(r#"{"Blue": 1}"#).is_err());
+}
+
+#[test]
+fn test_float() {
+    // 1.00000017881393432617187499 is just below the midpoint of two f32s;
+    // rounding it to f64 first lands exactly on the midpoint.
+    let v: f32 = from_str("1.00000017881393432617187499").unwrap();
+    assert_eq!(v, 1.0000001);
+    let v: f32 = from_str("0.1").unwrap();
+    assert_eq!(v, 0.1);
+    let v: Vec<f64> = from_str("[1, -2, 0.5, 1e-3, 2.2250738585072014e-308]").unwrap();
+    assert_eq!(v, [1.0, -2.0, 0.5, 1e-3, 2.2250738585072014e-308]);
+    let v: f64 = from_str("-0").unwrap();
+    assert!(v == 0.0 && v.is_sign_negative());
+    let v: Vec<f32> = from_str("[-0, -0.0]").unwrap();
+    assert!(v.iter().all(|v| *v == 0.0 && v.is_sign_negative()));
+    assert_eq!(from_str::<i64>("-0"), Ok(0));
+    let v: u64 = from_str("18446744073709551615").unwrap();
+    assert_eq!(v, u64::MAX);
+    assert!(from_str::<f64>("1e999").is_err());
+    assert!(from_str::<f64>("01").is_err());
+}
+
+#[test]
+fn test_expect() {
+    use std::collections::HashMap;
+
+    assert_eq!(
+        from_str::<Vec<u32>>("[1 2]").unwrap_err(),
+        Error::Wanted {
+            at: 3,
+            expected: ',',
+            found: '2'
+        }
+    );
+    assert_eq!(
+        from_str::<HashMap<String, u32>>(r#"{"a":1 "b":2}"#).unwrap_err(),
+        Error::Wanted {
+            at: 7,
+            expected: ',',
+            found: '"'
+        }
+    );
+    assert_eq!(
+        from_str::<HashMap<String, u32>>(r#"{"a" 1}"#).unwrap_err(),
+        Error::Wanted {
+            at: 5,
+            expected: ':',
+            found: '1'
+        }
+    );
+}
+
+#[test]
+fn test_structural_index_skip() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Keep<'a> {
+        keep: u32,
+        name: &'a str,
+    }
+
+    // The skipped value is never lexed, so its invalid `tru` goes unnoticed.
+    let j = r#"{"skip": [1, {"x": tru, "y": "]"}], "keep": 3, "more": {}, "name": "n"}"#;
+    let mut de = Deserializer::with_structural_index(j).unwrap();
+    let v = Keep::deserialize(&mut de).unwrap();
+    assert_eq!(v, Keep { keep: 3, name: "n" });
+    assert!(from_str::<Keep>(j).is_err());
+}
+
+#[test]
+fn test_skip_ignored() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Keep<'a> {
+        keep: u32,
+        name: &'a str,
+    }
+
+    let j = r#"{"junk": {"a\"": ["x\"]", 1e5, [true, null]]}, "keep": 1,
+                "skip": "\u00e9", "n": -2.5, "name": "n", "z": [[], {}]}"#;
+    assert_eq!(from_str::<Keep>(j).unwrap(), Keep { keep: 1, name: "n" });
+
+    let mut de = Deserializer::new(r#"[{"a": [1, "]"]}, 2]"#);
+    let (_, two): (de::IgnoredAny, u32) = de::Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(two, 2);
+
+    assert_eq!(
+        from_str::<de::IgnoredAny>(r#"{"a": "#).unwrap_err(),
+        Error::EofWhileParsingValue(6)
+    );
+    assert_eq!(
+        from_str::<Keep>(r#"{"junk": [1, {"a": 2]}, "keep": 1, "name": "n"}"#),
+        Err(Error::Unexpected(20, ']'))
+    );
+    assert!(from_str::<Keep>(r#"{"junk": [nul], "keep": 1, "name": "n"}"#).is_err());
+    assert!(from_str::<Keep>(r#"{"junk": [01], "keep": 1, "name": "n"}"#).is_err());
+    let deep = format!("{}{}", "[".repeat(100), "]".repeat(100));
+    assert!(from_str::<de::IgnoredAny>(&deep).is_ok());
+    let crossed = format!("{}}}{}", "[".repeat(70), "]".repeat(69));
+    assert_eq!(
+        from_str::<de::IgnoredAny>(&crossed).unwrap_err(),
+        Error::Unexpected(70, '}')
+    );
+}
+
+#[test]
+fn test_from_mut_str() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Note<'a> {
+        title: &'a str,
+        tags: Vec<&'a str>,
+    }
+
+    let mut s = String::from(
+        r#"{"ti\u0074le": "caf\u00e9 \"au\" lait\n", "tags": ["\ud83d\ude00\/x", "plain"]}"#,
+    );
+    let note: Note = from_mut_str(&mut s).unwrap();
+    assert_eq!(note.title, "caf\u{e9} \"au\" lait\n");
+    assert_eq!(note.tags, ["\u{1f600}/x", "plain"]);
+
+    // Leftovers of multi-byte chars moved down are blanked.
+    let mut bytes = b"[\"\\n\xe2\x82\xac\"]".to_vec();
+    let v: Vec<&str> = from_mut_slice(&mut bytes).unwrap();
+    assert_eq!(v, ["\n\u{20ac}"]);
+    drop(v);
+    assert!(std::str::from_utf8(&bytes).is_ok());
+
+    let mut bytes = b"[\"\\t\xff\"]".to_vec();
+    assert_eq!(
+        from_mut_slice::<Vec<&str>>(&mut bytes),
+        Err(Error::InvalidUtf8(4))
+    );
+    let mut s = String::from(r#"["\u00e9\q"]"#);
+    assert_eq!(
+        from_mut_str::<Vec<&str>>(&mut s),
+        Err(Error::InvalidEscape(9, 'q'))
+    );
+    assert_eq!(s, "[\"\u{e9}    \\q\"]");
+    let mut s = String::from("[\"\\n\\\u{e9}\"]");
+    assert_eq!(
+        from_mut_str::<Vec<&str>>(&mut s),
+        Err(Error::InvalidEscape(5, '\u{e9}'))
+    );
+    assert_eq!(s, "[\"\n \\\u{e9}\"]");
+    let mut s = String::from(r#""\ud800x""#);
+    assert_eq!(
+        from_mut_str::<&str>(&mut s),
+        Err(Error::InvalidEscapeValue(2, 0xd800))
+    );
+}
+
+#[test]
+fn test_key_interner() {
+    use crate::intern::Key;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    let input = r#"{"n\u0061me": 1, "id": 2} {"name": 3} [4]"#;
+    let mut stream = Deserializer::new(input)
+        .key_interner(KeyInterner::default())
+        .into_stream::<HashMap<Key, u32>>();
+    let first = stream.next().unwrap().unwrap();
+    let second = stream.next().unwrap().unwrap();
+    assert_eq!(first["name"], 1);
+    assert_eq!(second["name"], 3);
+    let shared = |m: &HashMap<Key, u32>| m.keys().find(|k| &***k == "name").cloned();
+    assert!(Arc::ptr_eq(
+        &shared(&first).unwrap().into_inner(),
+        &shared(&second).unwrap().into_inner()
+    ));
+    assert_eq!(stream.byte_offset(), input.find(" [4]").unwrap());
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+
+    let de = stream.into_deserializer();
+    assert_eq!(de.into_interner().unwrap().len(), 2);
+
+    // Escaped field names are decoded once, then found by their raw text.
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Named {
+        name: u32,
+    }
+    let input = r#"[{"n\u0061me": 1}, {"n\u0061me": 2}, {"name": 3}]"#;
+    let mut de = Deserializer::new(input).key_interner(KeyInterner::default());
+    let v = Vec::<Named>::deserialize(&mut de).unwrap();
+    assert_eq!(v, [Named { name: 1 }, Named { name: 2 }, Named { name: 3 }]);
+    let interner = de.into_interner().unwrap();
+    assert!(interner.get_escaped(r"n\u0061me").is_some());
+    assert_eq!(interner.len(), 1);
+
+    // Without an interner, keys are still read, just not shared.
+    let m: HashMap<Key, u32> = from_str(r#"{"a": 1}"#).unwrap();
+    assert_eq!(m["a"], 1);
+}
+
+#[test]
+fn test_numeric_array() {
+    let v: Vec<f64> = from_str(" [ 1.5 , -2e3,0, 7 ] ").unwrap();
+    assert_eq!(v, [1.5, -2e3, 0.0, 7.0]);
+    let v: Vec<f32> = from_str("[0.1, 16777217]").unwrap();
+    assert_eq!(v, [0.1f32, 16777216.0]);
+    let v: Vec<Vec<i64>> = from_str("[[1, -2], [], [3]]").unwrap();
+    assert_eq!(v, [vec![1, -2], vec![], vec![3]]);
+    let v: Vec<u8> = from_str("[]").unwrap();
+    assert!(v.is_empty());
+
+    assert_eq!(
+        from_str::<Vec<i64>>("[1 2]"),
+        Err(Error::Wanted {
+            at: 3,
+            expected: ',',
+            found: '2'
+        })
+    );
+    assert!(from_str::<Vec<i64>>("[1,]").is_err());
+    assert!(from_str::<Vec<i64>>("[1, 01]").is_err());
+    assert!(from_str::<Vec<u8>>("[1, 256]").is_err());
+    assert_eq!(
+        from_str::<Vec<i64>>("[1, 2"),
+        Err(Error::EofWhileParsingValue(5))
+    );
+
+    // The bulk readers accept what the serde path does, and fail the same
+    // way on elements of the wrong type.
+    let mut de = Deserializer::new(" [ 1.5 , -2e3,0, -0, 7 ] ");
+    let v = de.read_f64_array().unwrap();
+    assert_eq!(v, [1.5, -2e3, 0.0, -0.0, 7.0]);
+    assert!(v[3].is_sign_negative());
+    let v = Deserializer::new("[1, -2, 9223372036854775807]").read_i64_array();
+    assert_eq!(v, Ok(vec![1, -2, i64::MAX]));
+    assert!(Deserializer::new("[]").read_f64_array().unwrap().is_empty());
+    for bad in ["[1 2]", "[1,]", "[1, 01]", "[1, 2", "{}"] {
+        assert!(Deserializer::new(bad).read_i64_array().is_err(), "{bad}");
+        assert!(from_str::<Vec<i64>>(bad).is_err(), "{bad}");
+    }
+    for bad in [
+        "[1, true]",
+        "[[1]]",
+        "[1.5]",
+        "[9223372036854775808]",
+        "[\"1\"]",
+        "[null]",
+    ] {
+        assert_eq!(
+            Deserializer::new(bad).read_i64_array().err(),
+            from_str::<Vec<i64>>(bad).err(),
+            "{bad}"
+        );
+    }
+}
+
+#[test]
+fn test_duplicate_keys() {
+    use crate::value::Value;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Login {
+        role: String,
+        name: String,
+    }
+    fn read<'a, T: Deserialize<'a>>(s: &'a str, policy: DuplicateKeys) -> Result<T> {
+        T::deserialize(&mut Deserializer::new(s).duplicate_keys(policy))
+    }
+
+    let input = r#"{"role": "user", "name": "a", "role": {"x": [1]}, "role": "admin"}"#;
+    assert_eq!(
+        read::<Login>(input, DuplicateKeys::Error),
+        Err(Error::DuplicateKey {
+            key: "role".to_string(),
+            first: 1,
+            second: 30
+        })
+    );
+    let map: HashMap<String, Value> = read(input, DuplicateKeys::FirstWins).unwrap();
+    assert_eq!(map["role"], Value::String("user".to_string()));
+    let login: Login = read(input, DuplicateKeys::LastWins).unwrap();
+    assert_eq!(login.role, "admin");
+    assert!(read::<Login>(input, DuplicateKeys::Allow).is_err());
+
+    // Each object has its own keys, however deep.
+    let input = r#"{"a": {"a": 1, "b": 2, "a": 3}, "b": [{"a": 4}, {"a": 5, "a": 6}]}"#;
+    let first: Value = read(input, DuplicateKeys::FirstWins).unwrap();
+    let last: Value = read(input, DuplicateKeys::LastWins).unwrap();
+    assert_eq!(
+        crate::ser::to_string(&first).unwrap(),
+        r#"{"a":{"a":1,"b":2},"b":[{"a":4},{"a":5}]}"#
+    );
+    assert_eq!(
+        crate::ser::to_string(&last).unwrap(),
+        r#"{"a":{"a":3,"b":2},"b":[{"a":4},{"a":6}]}"#
+    );
+    assert!(read::<Value>(r#"{"a": {"b": 1}, "b": {"b": 2}}"#, DuplicateKeys::Error).is_ok());
+
+    // Looking ahead does not unescape in place.
+    let mut buf = r#"{"k\n": "x\ty", "k\u000a": "z\"", "k\n": "last\\"}"#.to_string();
+    let mut de = Deserializer::from_mut_str(&mut buf).duplicate_keys(DuplicateKeys::LastWins);
+    let map = HashMap::<&str, &str>::deserialize(&mut de).unwrap();
+    assert_eq!(map, [("k\n", "last\\")].into_iter().collect());
+
+    // Objects inside one read ahead keep their own keys, and the free
+    // functions take the policy too.
+    let input =
+        r#"[{"a": {"b": {"c": 1, "c": 2}, "b": {"d": [{"e": 3, "e": 4}]}}, "a": 5}, {"a": 6}]"#;
+    let last: Value = from_str_with(input, DuplicateKeys::LastWins).unwrap();
+    assert_eq!(
+        crate::ser::to_string(&last).unwrap(),
+        r#"[{"a":5},{"a":6}]"#
+    );
+    let input = r#"{"x": {"b": {"c": 1, "c": 2}, "b": {"d": [{"e": 3, "e": 4}], "f": {}}}}"#;
+    let last: Value = from_str_with(input, DuplicateKeys::LastWins).unwrap();
+    assert_eq!(
+        crate::ser::to_string(&last).unwrap(),
+        r#"{"x":{"b":{"d":[{"e":4}],"f":{}}}}"#
+    );
+    let mut bytes = br#"{"a": 1, "a": 2}"#.to_vec();
+    assert!(from_mut_slice_with::<Value>(&mut bytes, DuplicateKeys::Error).is_err());
+}
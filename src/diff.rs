@@ -0,0 +1,365 @@
+//! Semantic comparison of JSON documents.
+//!
+//! Documents are compared as values, not text: member order, whitespace and
+//! the spelling of numbers (`1`, `1.0`, `1e0`) make no difference. The
+//! differences are reported by JSON Pointer and can be turned into an
+//! RFC 6902 patch with [`to_patch`].
+
+use crate::de::DuplicateKeys;
+use crate::patch::Operation;
+use crate::pointer;
+use crate::tokenizer::Result;
+use crate::value::{Map, Number, Value};
+
+/// How values are compared.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    tolerance: f64,
+    arrays_as_sets: bool,
+    duplicate_keys: DuplicateKeys,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Treats numbers at most `tolerance` apart as equal.
+    pub fn float_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Compares arrays as sets: order is ignored, and elements are matched
+    /// to equal elements of the other array. Elements only in the new array
+    /// are reported as added at `-`, the end of the array.
+    pub fn arrays_as_sets(mut self, arrays_as_sets: bool) -> Self {
+        self.arrays_as_sets = arrays_as_sets;
+        self
+    }
+
+    /// Sets how [`equal_str`] and [`diff_str`] read objects with a repeated
+    /// key.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+}
+
+/// One difference between two documents.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        from: Value,
+        to: Value,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Whether `a` and `b` are the same document under `options`. Two integers
+/// are only equal if they are exactly so; a nonzero tolerance applies when
+/// one side is a float.
+pub fn equal(a: &Value, b: &Value, options: &Options) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let float = matches!(a, Number::F64(_)) || matches!(b, Number::F64(_));
+            a.same_as(*b)
+                || float
+                    && options.tolerance > 0.0
+                    && (a.as_f64() - b.as_f64()).abs() <= options.tolerance
+        }
+        (Value::Array(a), Value::Array(b)) if options.arrays_as_sets => {
+            a.len() == b.len() && match_elements(a, b, options).iter().all(Option::is_some)
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b, options))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, v)| b.get(k).is_some_and(|w| equal(v, w, options)))
+        }
+        _ => a == b,
+    }
+}
+
+/// The changes that turn `from` into `to`. They are listed in an order in
+/// which they can be applied one after another: removals from an array
+/// come after its other changes, highest index first.
+pub fn diff(from: &Value, to: &Value, options: &Options) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_values(&mut String::new(), from, to, options, &mut changes);
+    changes
+}
+
+/// Compares the document texts `a` and `b`.
+pub fn equal_str(a: &str, b: &str, options: &Options) -> Result<bool> {
+    let a: Value = crate::de::from_str_with(a, options.duplicate_keys)?;
+    let b: Value = crate::de::from_str_with(b, options.duplicate_keys)?;
+    Ok(equal(&a, &b, options))
+}
+
+/// The changes that turn the document text `from` into `to`.
+pub fn diff_str(from: &str, to: &str, options: &Options) -> Result<Vec<Change>> {
+    let from: Value = crate::de::from_str_with(from, options.duplicate_keys)?;
+    let to: Value = crate::de::from_str_with(to, options.duplicate_keys)?;
+    Ok(diff(&from, &to, options))
+}
+
+/// The RFC 6902 patch that makes the changes.
+pub fn to_patch(changes: &[Change]) -> Vec<Operation> {
+    changes
+        .iter()
+        .map(|change| match change.clone() {
+            Change::Added { path, value } => Operation::Add { path, value },
+            Change::Removed { path, .. } => Operation::Remove { path },
+            Change::Changed { path, to, .. } => Operation::Replace { path, value: to },
+        })
+        .collect()
+}
+
+fn diff_values(
+    path: &mut String,
+    from: &Value,
+    to: &Value,
+    options: &Options,
+    changes: &mut Vec<Change>,
+) {
+    if equal(from, to, options) {
+        return;
+    }
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => diff_members(path, from, to, options, changes),
+        (Value::Array(from), Value::Array(to)) if options.arrays_as_sets => {
+            let matched = match_elements(to, from, options);
+            let mut used = vec![false; from.len()];
+            for (i, value) in to.iter().enumerate() {
+                match matched[i] {
+                    Some(j) => used[j] = true,
+                    None => changes.push(Change::Added {
+                        path: format!("{}/-", path),
+                        value: value.clone(),
+                    }),
+                }
+            }
+            for (j, value) in from.iter().enumerate().rev() {
+                if !used[j] {
+                    changes.push(Change::Removed {
+                        path: format!("{}/{}", path, j),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(from), Value::Array(to)) => {
+            let len = path.len();
+            for (i, (a, b)) in from.iter().zip(to).enumerate() {
+                path.push_str(&format!("/{}", i));
+                diff_values(path, a, b, options, changes);
+                path.truncate(len);
+            }
+            for (i, value) in to.iter().enumerate().skip(from.len()) {
+                changes.push(Change::Added {
+                    path: format!("{}/{}", path, i),
+                    value: value.clone(),
+                });
+            }
+            for (i, value) in from.iter().enumerate().skip(to.len()).rev() {
+                changes.push(Change::Removed {
+                    path: format!("{}/{}", path, i),
+                    value: value.clone(),
+                });
+            }
+        }
+        (from, to) => changes.push(Change::Changed {
+            path: path.clone(),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+    }
+}
+
+fn diff_members(
+    path: &mut String,
+    from: &Map,
+    to: &Map,
+    options: &Options,
+    changes: &mut Vec<Change>,
+) {
+    let len = path.len();
+    for (key, value) in from {
+        path.push('/');
+        path.push_str(&pointer::escape(key));
+        match to.get(key) {
+            Some(new) => diff_values(path, value, new, options, changes),
+            None => changes.push(Change::Removed {
+                path: path.clone(),
+                value: value.clone(),
+            }),
+        }
+        path.truncate(len);
+    }
+    for (key, value) in to {
+        if !from.contains_key(key) {
+            changes.push(Change::Added {
+                path: format!("{}/{}", path, pointer::escape(key)),
+                value: value.clone(),
+            });
+        }
+    }
+}
+
+/// For each element of `a`, the index of a distinct equal element of `b`.
+fn match_elements(a: &[Value], b: &[Value], options: &Options) -> Vec<Option<usize>> {
+    // The element of `a` each element of `b` is paired with.
+    let mut owner = vec![None; b.len()];
+    let mut unmatched = Vec::new();
+    for (i, value) in a.iter().enumerate() {
+        match (0..b.len()).find(|&j| owner[j].is_none() && equal(value, &b[j], options)) {
+            Some(j) => owner[j] = Some(i),
+            None => unmatched.push(i),
+        }
+    }
+    // Without a tolerance equality is transitive, so first fit already
+    // pairs as many elements as can be paired. With one, 1.5 may take the
+    // 1.0 that only 1.0 could have, so earlier pairs are moved along
+    // augmenting paths to make room.
+    if options.tolerance > 0.0 {
+        for i in unmatched {
+            augment(i, a, b, options, &mut owner);
+        }
+    }
+    let mut matched = vec![None; a.len()];
+    for (j, i) in owner.into_iter().enumerate() {
+        if let Some(i) = i {
+            matched[i] = Some(j);
+        }
+    }
+    matched
+}
+
+/// Pairs `a[i]` with an element of `b`, moving earlier pairs to other
+/// elements of `b` if that makes one free (Kuhn's algorithm). Whether it
+/// succeeded.
+fn augment(
+    i: usize,
+    a: &[Value],
+    b: &[Value],
+    options: &Options,
+    owner: &mut [Option<usize>],
+) -> bool {
+    let mut seen = vec![false; b.len()];
+    // The elements of `a` on the path, each with the next element of `b` to
+    // try, and the element of `b` that led from each to the next.
+    let mut path = vec![(i, 0)];
+    let mut taken = Vec::new();
+    while let Some((x, next)) = path.last_mut() {
+        let x = *x;
+        match (*next..b.len()).find(|&j| !seen[j] && equal(&a[x], &b[j], options)) {
+            Some(j) => {
+                *next = j + 1;
+                seen[j] = true;
+                match owner[j] {
+                    Some(k) => {
+                        taken.push(j);
+                        path.push((k, 0));
+                    }
+                    None => {
+                        owner[j] = Some(x);
+                        for (&(x, _), &j) in path.iter().zip(&taken) {
+                            owner[j] = Some(x);
+                        }
+                        return true;
+                    }
+                }
+            }
+            None => {
+                path.pop();
+                taken.pop();
+            }
+        }
+    }
+    false
+}
+
+#[test]
+fn test_diff() {
+    let exact = Options::new();
+    assert!(equal_str(
+        r#"{"a": 1, "b": [1e0, "x"]}"#,
+        r#"{ "b":[1.0,"x"],"a":1 }"#,
+        &exact
+    )
+    .unwrap());
+    assert!(!equal_str("[1, 2]", "[2, 1]", &exact).unwrap());
+    assert!(equal_str("[1, 2, 2]", "[2, 1, 2]", &exact.arrays_as_sets(true)).unwrap());
+    assert!(!equal_str("[1, 1, 2]", "[2, 1, 2]", &exact.arrays_as_sets(true)).unwrap());
+    assert!(!equal_str("0.1", "0.1000001", &exact).unwrap());
+    assert!(equal_str("0.1", "0.1000001", &exact.float_tolerance(1e-6)).unwrap());
+    // Integers past 2^53 are compared exactly, with or without a tolerance.
+    let (big, bigger) = ("9007199254740992", "9007199254740993");
+    assert!(!equal_str(big, bigger, &exact).unwrap());
+    assert!(!equal_str(big, bigger, &exact.float_tolerance(0.5)).unwrap());
+    assert!(!equal_str("9007199254740992.0", bigger, &exact).unwrap());
+    assert!(equal_str("9007199254740992.0", bigger, &exact.float_tolerance(1.0)).unwrap());
+    assert_eq!(diff_str(big, bigger, &exact).unwrap().len(), 1);
+
+    let from = r#"{"a": {"b": 1, "c": [1, 2, 3]}, "d": "x", "e~/": true}"#;
+    let to = r#"{"a": {"b": 2, "c": [1, 5]}, "d": "x", "f": null}"#;
+    let changes = diff_str(from, to, &exact).unwrap();
+    let paths: Vec<&str> = changes.iter().map(Change::path).collect();
+    assert_eq!(paths, ["/a/b", "/a/c/1", "/a/c/2", "/e~0~1", "/f"]);
+    assert_eq!(
+        changes[2],
+        Change::Removed {
+            path: "/a/c/2".to_string(),
+            value: Value::Number(crate::value::Number::U64(3)),
+        }
+    );
+
+    let patch = crate::ser::to_string(&to_patch(&changes)).unwrap();
+    assert_eq!(
+        crate::patch::apply_str(from, &patch).unwrap(),
+        crate::patch::apply_str(to, "[]").unwrap()
+    );
+
+    let sets = Options::new().arrays_as_sets(true);
+    let changes = diff_str("[1, 2, 3, 4]", "[4, 5, 2]", &sets).unwrap();
+    let paths: Vec<&str> = changes.iter().map(Change::path).collect();
+    assert_eq!(paths, ["/-", "/2", "/0"]);
+    let patch = crate::ser::to_string(&to_patch(&changes)).unwrap();
+    let patched = crate::patch::apply_str("[1, 2, 3, 4]", &patch).unwrap();
+    assert!(equal_str(&patched, "[4, 5, 2]", &sets).unwrap());
+
+    assert_eq!(diff_str("1", "1.0", &exact).unwrap(), []);
+    assert_eq!(diff_str("1", "[1]", &exact).unwrap()[0].path(), "");
+
+    // A tolerance makes equality intransitive, so first fit is not enough.
+    let near = sets.float_tolerance(0.5);
+    assert!(equal_str("[1.5, 1.0]", "[1.0, 2.0]", &near).unwrap());
+    assert_eq!(diff_str("[1.0, 2.0]", "[1.5, 1.0]", &near).unwrap(), []);
+    assert!(!equal_str("[1.5, 1.0]", "[1.0, 3.0]", &near).unwrap());
+
+    let first = Options::new().duplicate_keys(DuplicateKeys::FirstWins);
+    assert!(!equal_str(r#"{"a": 1, "a": 2}"#, r#"{"a": 1}"#, &exact).unwrap());
+    assert!(equal_str(r#"{"a": 1, "a": 2}"#, r#"{"a": 1}"#, &first).unwrap());
+    let error = Options::new().duplicate_keys(DuplicateKeys::Error);
+    assert!(diff_str(r#"{"a": 1, "a": 2}"#, "{}", &error).is_err());
+}
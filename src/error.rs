@@ -22,17 +22,58 @@ pub enum Error {
         expected: char,
         found: char,
     },
+    DuplicateKey {
+        key: String,
+        first: usize,
+        second: usize,
+    },
 
     InvalidNumber(String),
+    InvalidUtf8(usize),
+    InvalidPointer(String),
+    InvalidPath(usize),
+    Io(String),
+    PatchFailed(usize, String),
+    InvalidSchema(String),
     NumberOutOfRange,
     NotSupportedChar(char, usize),
 
     OpNotExist(String),
     JSONKeyMustBeString(),
+    InvalidMapKey(&'static str),
+    NonFiniteFloat(&'static str),
     InvalidStructString(),
     InvalidEnumString(),
 }
 
+impl Error {
+    /// Moves the input offset an error reports by `by`, for input that was
+    /// lexed a piece at a time.
+    pub(crate) fn shifted(self, by: usize) -> Error {
+        match self {
+            Error::InvalidCharInString(at, c) => Error::InvalidCharInString(at + by, c),
+            Error::InvalidEscape(at, c) => Error::InvalidEscape(at + by, c),
+            Error::InvalidHexEscape(at, c) => Error::InvalidHexEscape(at + by, c),
+            Error::InvalidEscapeValue(at, v) => Error::InvalidEscapeValue(at + by, v),
+            Error::Unexpected(at, c) => Error::Unexpected(at + by, c),
+            Error::UnterminatedString(at) => Error::UnterminatedString(at + by),
+            Error::EofWhileParsingValue(at) => Error::EofWhileParsingValue(at + by),
+            Error::Wanted {
+                at,
+                expected,
+                found,
+            } => Error::Wanted {
+                at: at + by,
+                expected,
+                found,
+            },
+            Error::InvalidUtf8(at) => Error::InvalidUtf8(at + by),
+            Error::NotSupportedChar(c, at) => Error::NotSupportedChar(c, at + by),
+            other => other,
+        }
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
@@ -54,7 +95,30 @@ impl de::Error for Error {
 impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match &self {
-            Error::Message(msg) => formatter.write_str(&msg),
+            Error::Message(msg) => formatter.write_str(msg),
+            Error::InvalidMapKey(kind) => write!(
+                formatter,
+                "map key must be a string, integer, bool, char, finite float or unit variant, found {}",
+                kind
+            ),
+            Error::NonFiniteFloat(v) => {
+                write!(formatter, "cannot serialize non-finite float {}", v)
+            }
+            Error::InvalidUtf8(pos) => write!(formatter, "invalid UTF-8 at byte {}", pos),
+            Error::InvalidPointer(pointer) => {
+                write!(formatter, "invalid JSON pointer {:?}", pointer)
+            }
+            Error::InvalidPath(pos) => write!(formatter, "invalid JSONPath at byte {}", pos),
+            Error::Io(msg) => write!(formatter, "I/O error: {}", msg),
+            Error::PatchFailed(op, reason) => {
+                write!(formatter, "patch operation {} failed: {}", op, reason)
+            }
+            Error::InvalidSchema(path) => write!(formatter, "invalid schema at {:?}", path),
+            Error::DuplicateKey { key, first, second } => write!(
+                formatter,
+                "duplicate key {:?} at byte {}, first at byte {}",
+                key, second, first
+            ),
             _ => formatter.write_str("unexpected end of input"),
         }
     }
@@ -0,0 +1,548 @@
+//! Number scanning and correctly rounded decimal to binary float conversion.
+//!
+//! The scanner splits a JSON number into a sign, up to 19 significant digits
+//! and a decimal exponent in a single pass. Conversion then tries the Clinger
+//! fast path, then the Eisel-Lemire algorithm, and only falls back to the
+//! exact (but slower) standard library parser for the rare inputs that the
+//! 128-bit approximation cannot decide.
+
+mod table;
+
+use crate::error::Error;
+use crate::token::ParseNumber;
+use crate::tokenizer::Result;
+use std::str::FromStr;
+use table::{LARGEST_POWER_OF_FIVE, POWER_OF_FIVE_128, SMALLEST_POWER_OF_FIVE};
+
+/// The smallest 19-digit integer; mantissas stop accumulating at this size.
+const MIN_19DIGIT_INT: u64 = 100_0000_0000_0000_0000;
+
+/// A decimal number `mantissa * 10^exponent`, kept unconverted so it can be
+/// rounded once, directly to the width the caller asks for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decimal<'a> {
+    negative: bool,
+    mantissa: u64,
+    exponent: i64,
+    /// More than 19 significant digits were seen, so `mantissa` is truncated.
+    many_digits: bool,
+    text: &'a str,
+}
+
+/// Scans the JSON number at the start of `s`, returning it together with the
+/// number of bytes it spans. Follows the JSON grammar strictly: no leading
+/// `+`, no leading zeros, and at least one digit after `.` and `e`.
+pub fn scan(s: &str) -> Result<(ParseNumber<'_>, usize)> {
+    let bytes = s.as_bytes();
+    let digit_at = |i: usize| bytes.get(i).filter(|b| b.is_ascii_digit()).copied();
+
+    let mut i = 0;
+    let negative = bytes.first() == Some(&b'-');
+    if negative {
+        i += 1;
+    }
+
+    let int_start = i;
+    let mut mantissa: u64 = 0;
+    match digit_at(i) {
+        Some(b'0') => {
+            i += 1;
+            if digit_at(i).is_some() {
+                return Err(invalid(s, i));
+            }
+        }
+        Some(_) => {
+            while let Some(d) = digit_at(i) {
+                mantissa = mantissa.wrapping_mul(10).wrapping_add((d - b'0') as u64);
+                i += 1;
+            }
+        }
+        None => return Err(invalid(s, i)),
+    }
+    let int_end = i;
+    let mut n_digits = int_end - int_start;
+
+    let mut frac_start = i;
+    let mut frac_end = i;
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        frac_start = i;
+        while let Some(d) = digit_at(i) {
+            mantissa = mantissa.wrapping_mul(10).wrapping_add((d - b'0') as u64);
+            i += 1;
+        }
+        frac_end = i;
+        if frac_end == frac_start {
+            return Err(invalid(s, i));
+        }
+        n_digits += frac_end - frac_start;
+    }
+
+    let mut exp_number: i64 = 0;
+    let has_exponent = matches!(bytes.get(i), Some(b'e' | b'E'));
+    if has_exponent {
+        i += 1;
+        let exp_negative = match bytes.get(i) {
+            Some(b'-') => {
+                i += 1;
+                true
+            }
+            Some(b'+') => {
+                i += 1;
+                false
+            }
+            _ => false,
+        };
+        if digit_at(i).is_none() {
+            return Err(invalid(s, i));
+        }
+        while let Some(d) = digit_at(i) {
+            // Anything this large is already zero or infinite.
+            if exp_number < 0x10000 {
+                exp_number = 10 * exp_number + (d - b'0') as i64;
+            }
+            i += 1;
+        }
+        if exp_negative {
+            exp_number = -exp_number;
+        }
+    }
+    let text = &s[..i];
+
+    if frac_start == frac_end && !has_exponent && n_digits <= 20 {
+        // Twenty digits may or may not fit in a u64, so redo those checked.
+        let int = if n_digits == 20 {
+            bytes[int_start..int_end].iter().try_fold(0u64, |acc, d| {
+                acc.checked_mul(10)?.checked_add((d - b'0') as u64)
+            })
+        } else {
+            Some(mantissa)
+        };
+        match (int, negative) {
+            (Some(int), false) => return Ok((ParseNumber::U64(int), i)),
+            (Some(int), true) if int <= i64::MIN.unsigned_abs() => {
+                return Ok((ParseNumber::I64((int as i64).wrapping_neg()), i))
+            }
+            _ => {}
+        }
+    }
+
+    let mut exponent = exp_number - (frac_end - frac_start) as i64;
+    let mut many_digits = false;
+    if n_digits > 19 {
+        // Leading zeros are not significant.
+        let mut start = int_start;
+        while start < frac_end && matches!(bytes[start], b'0' | b'.') {
+            if bytes[start] == b'0' {
+                n_digits -= 1;
+            }
+            start += 1;
+        }
+        if n_digits > 19 {
+            many_digits = true;
+            mantissa = 0;
+            let mut p = int_start;
+            while mantissa < MIN_19DIGIT_INT && p < int_end {
+                mantissa = mantissa * 10 + (bytes[p] - b'0') as u64;
+                p += 1;
+            }
+            if mantissa >= MIN_19DIGIT_INT {
+                exponent = (int_end - p) as i64 + exp_number;
+            } else {
+                p = frac_start;
+                while mantissa < MIN_19DIGIT_INT && p < frac_end {
+                    mantissa = mantissa * 10 + (bytes[p] - b'0') as u64;
+                    p += 1;
+                }
+                exponent = frac_start as i64 - p as i64 + exp_number;
+            }
+        }
+    }
+
+    let decimal = Decimal {
+        negative,
+        mantissa,
+        exponent,
+        many_digits,
+        text,
+    };
+    Ok((ParseNumber::Float(decimal), i))
+}
+
+fn invalid(s: &str, end: usize) -> Error {
+    let end = s[end..]
+        .char_indices()
+        .nth(1)
+        .map_or(s.len(), |(i, _)| end + i);
+    Error::InvalidNumber(s[..end].to_string())
+}
+
+impl Decimal<'_> {
+    pub fn to_f64(self) -> Result<f64> {
+        self.to_float()
+    }
+
+    pub fn to_f32(self) -> Result<f32> {
+        self.to_float()
+    }
+
+    fn to_float<F: Float>(self) -> Result<F> {
+        let value = match self.fast_path() {
+            Some(value) => value,
+            None => {
+                let mut fp = compute_float::<F>(self.exponent, self.mantissa);
+                // With a truncated mantissa the true value lies between
+                // `mantissa` and `mantissa + 1`; both must round the same way.
+                if self.many_digits
+                    && fp.e >= 0
+                    && fp != compute_float::<F>(self.exponent, self.mantissa + 1)
+                {
+                    fp.e = -1;
+                }
+                if fp.e < 0 {
+                    self.text
+                        .parse()
+                        .map_err(|_| Error::InvalidNumber(self.text.to_string()))?
+                } else {
+                    let bits = fp.f | ((fp.e as u64) << F::MANTISSA_EXPLICIT_BITS);
+                    let value = F::from_u64_bits(bits);
+                    if self.negative {
+                        -value
+                    } else {
+                        value
+                    }
+                }
+            }
+        };
+        if value.is_infinite() {
+            return Err(Error::NumberOutOfRange);
+        }
+        Ok(value)
+    }
+
+    /// Clinger's fast path: when the mantissa and the power of ten are both
+    /// exactly representable, a single multiplication or division is
+    /// correctly rounded.
+    fn fast_path<F: Float>(&self) -> Option<F> {
+        if self.many_digits
+            || self.exponent < F::MIN_EXPONENT_FAST_PATH
+            || self.exponent > F::MAX_EXPONENT_DISGUISED_FAST_PATH
+            || self.mantissa > F::MAX_MANTISSA_FAST_PATH
+        {
+            return None;
+        }
+        let value = if self.exponent <= F::MAX_EXPONENT_FAST_PATH {
+            let value = F::from_u64(self.mantissa);
+            if self.exponent < 0 {
+                value / F::pow10_fast_path((-self.exponent) as usize)
+            } else {
+                value * F::pow10_fast_path(self.exponent as usize)
+            }
+        } else {
+            // Move the excess power of ten into the mantissa, e.g. `12e20`
+            // becomes `1200e18`, if the result is still exact.
+            let shift = self.exponent - F::MAX_EXPONENT_FAST_PATH;
+            let mantissa = self.mantissa.checked_mul(INT_POW10[shift as usize])?;
+            if mantissa > F::MAX_MANTISSA_FAST_PATH {
+                return None;
+            }
+            F::from_u64(mantissa) * F::pow10_fast_path(F::MAX_EXPONENT_FAST_PATH as usize)
+        };
+        Some(if self.negative { -value } else { value })
+    }
+}
+
+const INT_POW10: [u64; 16] = [
+    1,
+    10,
+    100,
+    1000,
+    10000,
+    100000,
+    1000000,
+    10000000,
+    100000000,
+    1000000000,
+    10000000000,
+    100000000000,
+    1000000000000,
+    10000000000000,
+    100000000000000,
+    1000000000000000,
+];
+
+/// The binary floating point formats we can round to.
+trait Float:
+    Copy
+    + PartialEq
+    + FromStr
+    + std::ops::Neg<Output = Self>
+    + std::ops::Mul<Output = Self>
+    + std::ops::Div<Output = Self>
+{
+    const MANTISSA_EXPLICIT_BITS: u32;
+    const MINIMUM_EXPONENT: i32;
+    const INFINITE_POWER: i32;
+    const SMALLEST_POWER_OF_TEN: i64;
+    const LARGEST_POWER_OF_TEN: i64;
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64;
+    const MIN_EXPONENT_FAST_PATH: i64;
+    const MAX_EXPONENT_FAST_PATH: i64;
+    const MAX_EXPONENT_DISGUISED_FAST_PATH: i64;
+    const MAX_MANTISSA_FAST_PATH: u64;
+
+    fn from_u64(v: u64) -> Self;
+    fn from_u64_bits(bits: u64) -> Self;
+    fn pow10_fast_path(exponent: usize) -> Self;
+    fn is_infinite(self) -> bool;
+}
+
+impl Float for f64 {
+    const MANTISSA_EXPLICIT_BITS: u32 = 52;
+    const MINIMUM_EXPONENT: i32 = -1023;
+    const INFINITE_POWER: i32 = 0x7FF;
+    const SMALLEST_POWER_OF_TEN: i64 = -342;
+    const LARGEST_POWER_OF_TEN: i64 = 308;
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64 = -4;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64 = 23;
+    const MIN_EXPONENT_FAST_PATH: i64 = -22;
+    const MAX_EXPONENT_FAST_PATH: i64 = 22;
+    const MAX_EXPONENT_DISGUISED_FAST_PATH: i64 = 37;
+    const MAX_MANTISSA_FAST_PATH: u64 = 2 << 52;
+
+    fn from_u64(v: u64) -> Self {
+        v as f64
+    }
+
+    fn from_u64_bits(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+
+    fn pow10_fast_path(exponent: usize) -> Self {
+        const TABLE: [f64; 23] = [
+            1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15,
+            1e16, 1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+        ];
+        TABLE[exponent]
+    }
+
+    fn is_infinite(self) -> bool {
+        f64::is_infinite(self)
+    }
+}
+
+impl Float for f32 {
+    const MANTISSA_EXPLICIT_BITS: u32 = 23;
+    const MINIMUM_EXPONENT: i32 = -127;
+    const INFINITE_POWER: i32 = 0xFF;
+    const SMALLEST_POWER_OF_TEN: i64 = -65;
+    const LARGEST_POWER_OF_TEN: i64 = 38;
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64 = -17;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64 = 10;
+    const MIN_EXPONENT_FAST_PATH: i64 = -10;
+    const MAX_EXPONENT_FAST_PATH: i64 = 10;
+    const MAX_EXPONENT_DISGUISED_FAST_PATH: i64 = 17;
+    const MAX_MANTISSA_FAST_PATH: u64 = 2 << 23;
+
+    fn from_u64(v: u64) -> Self {
+        v as f32
+    }
+
+    fn from_u64_bits(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+
+    fn pow10_fast_path(exponent: usize) -> Self {
+        const TABLE: [f32; 11] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+        TABLE[exponent]
+    }
+
+    fn is_infinite(self) -> bool {
+        f32::is_infinite(self)
+    }
+}
+
+/// A float as its biased exponent and explicit mantissa bits. A negative
+/// exponent means the result could not be determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BiasedFp {
+    f: u64,
+    e: i32,
+}
+
+impl BiasedFp {
+    fn zero_pow2(e: i32) -> Self {
+        BiasedFp { f: 0, e }
+    }
+}
+
+/// The Eisel-Lemire algorithm: computes `w * 10^q` rounded to nearest, even,
+/// from a 128-bit approximation of `5^q`.
+///
+/// See Daniel Lemire, "Number Parsing at a Gigabyte per Second".
+fn compute_float<F: Float>(q: i64, mut w: u64) -> BiasedFp {
+    let fp_zero = BiasedFp::zero_pow2(0);
+    let fp_inf = BiasedFp::zero_pow2(F::INFINITE_POWER);
+    let fp_error = BiasedFp::zero_pow2(-1);
+
+    if w == 0 || q < F::SMALLEST_POWER_OF_TEN {
+        return fp_zero;
+    } else if q > F::LARGEST_POWER_OF_TEN {
+        return fp_inf;
+    }
+    let lz = w.leading_zeros();
+    w <<= lz;
+    let (lo, hi) = compute_product_approx(q, w, F::MANTISSA_EXPLICIT_BITS as usize + 3);
+    if lo == u64::MAX {
+        // The truncated product may be one short of the halfway point.
+        // That only matters outside the range where `5^q` is exact.
+        let inside_safe_exponent = (-27..=55).contains(&q);
+        if !inside_safe_exponent {
+            return fp_error;
+        }
+    }
+    let upperbit = (hi >> 63) as i32;
+    let shift = upperbit + 64 - F::MANTISSA_EXPLICIT_BITS as i32 - 3;
+    let mut mantissa = hi >> shift;
+    let mut power2 = power(q as i32) + upperbit - lz as i32 - F::MINIMUM_EXPONENT;
+    if power2 <= 0 {
+        if -power2 + 1 >= 64 {
+            return fp_zero;
+        }
+        // A subnormal value.
+        mantissa >>= -power2 + 1;
+        mantissa += mantissa & 1;
+        mantissa >>= 1;
+        power2 = (mantissa >= (1_u64 << F::MANTISSA_EXPLICIT_BITS)) as i32;
+        return BiasedFp {
+            f: mantissa,
+            e: power2,
+        };
+    }
+    // Exactly halfway between two floats: round down to even instead of up.
+    if lo <= 1
+        && q >= F::MIN_EXPONENT_ROUND_TO_EVEN
+        && q <= F::MAX_EXPONENT_ROUND_TO_EVEN
+        && mantissa & 3 == 1
+        && (mantissa << shift) == hi
+    {
+        mantissa &= !1_u64;
+    }
+    mantissa += mantissa & 1;
+    mantissa >>= 1;
+    if mantissa >= (2_u64 << F::MANTISSA_EXPLICIT_BITS) {
+        // Rounding carried into a new bit.
+        mantissa = 1_u64 << F::MANTISSA_EXPLICIT_BITS;
+        power2 += 1;
+    }
+    mantissa &= !(1_u64 << F::MANTISSA_EXPLICIT_BITS);
+    if power2 >= F::INFINITE_POWER {
+        return fp_inf;
+    }
+    BiasedFp {
+        f: mantissa,
+        e: power2,
+    }
+}
+
+/// `floor(log2(10^q)) + 63`, exact for `q` in `[-1233, 1233]`.
+fn power(q: i32) -> i32 {
+    (q.wrapping_mul(152_170 + 65536) >> 16) + 63
+}
+
+fn full_multiplication(a: u64, b: u64) -> (u64, u64) {
+    let r = (a as u128) * (b as u128);
+    (r as u64, (r >> 64) as u64)
+}
+
+/// Multiplies `w` by `5^q`, computing the low word only when the high word
+/// alone cannot decide the rounding.
+fn compute_product_approx(q: i64, w: u64, precision: usize) -> (u64, u64) {
+    debug_assert!(q >= SMALLEST_POWER_OF_FIVE as i64);
+    debug_assert!(q <= LARGEST_POWER_OF_FIVE as i64);
+    let mask = if precision < 64 {
+        u64::MAX >> precision
+    } else {
+        u64::MAX
+    };
+    let index = (q - SMALLEST_POWER_OF_FIVE as i64) as usize;
+    let (hi5, lo5) = POWER_OF_FIVE_128[index];
+    let (mut first_lo, mut first_hi) = full_multiplication(w, hi5);
+    if first_hi & mask == mask {
+        let (_, second_hi) = full_multiplication(w, lo5);
+        first_lo = first_lo.wrapping_add(second_hi);
+        if second_hi > first_lo {
+            first_hi += 1;
+        }
+    }
+    (first_lo, first_hi)
+}
+
+#[test]
+fn test_parse_float() {
+    fn check(s: &str) {
+        let (number, len) = scan(s).unwrap();
+        assert_eq!(len, s.len(), "{}", s);
+        assert_eq!(
+            number.to_f64().unwrap().to_bits(),
+            s.parse::<f64>().unwrap().to_bits(),
+            "{}",
+            s
+        );
+        let expected = s.parse::<f32>().unwrap();
+        if expected.is_finite() {
+            assert_eq!(
+                number.to_f32().unwrap().to_bits(),
+                expected.to_bits(),
+                "{}",
+                s
+            );
+        } else {
+            assert_eq!(number.to_f32(), Err(Error::NumberOutOfRange), "{}", s);
+        }
+    }
+
+    for s in [
+        "0.1",
+        "-0.0",
+        "1e23",
+        "9007199254740993.0",
+        "2.2250738585072011e-308",
+        "4.9e-324",
+        "1.7976931348623157e308",
+        "0.000000000000000000000000000000000000000000001",
+        "123456789012345678901234567890",
+        "3.4028234664e38",
+        "1.00000017881393432617187499",
+        "7.038531e-26",
+        "0.1000000000000000055511151231257827021181583404541015625",
+        "12e20",
+        "-18446744073709551616",
+    ] {
+        check(s);
+    }
+
+    assert_eq!(scan("42,").unwrap(), (ParseNumber::U64(42), 2));
+    assert_eq!(scan("-42]").unwrap(), (ParseNumber::I64(-42), 3));
+    assert_eq!(
+        scan("18446744073709551615").unwrap().0,
+        ParseNumber::U64(u64::MAX)
+    );
+    assert_eq!(
+        scan("-9223372036854775808").unwrap().0,
+        ParseNumber::I64(i64::MIN)
+    );
+    let zero = scan("-0").unwrap().0;
+    assert_eq!(zero, ParseNumber::I64(0));
+    assert!(zero.to_f64().unwrap().is_sign_negative());
+    assert!(zero.to_f32().unwrap().is_sign_negative());
+    assert!(scan("0").unwrap().0.to_f64().unwrap().is_sign_positive());
+    assert_eq!(
+        scan("1e400").unwrap().0.to_f64(),
+        Err(Error::NumberOutOfRange)
+    );
+    for s in ["-", "01", "1.", "1.e5", "1e", "1e+", ".5"] {
+        assert!(scan(s).is_err(), "{}", s);
+    }
+}
@@ -0,0 +1,270 @@
+//! A structural index over a JSON document, in the style of simdjson's
+//! stage 1.
+//!
+//! One pass over the input finds the position of every `{`, `}`, `[`, `]`,
+//! `:` and `,` outside of strings, and pairs up the brackets. The input is
+//! taken in 64-byte blocks: each byte is classified by a table lookup into
+//! bitmasks of quotes, backslashes and structurals, and the string interiors
+//! of a whole block are then masked off at once with bit operations. With
+//! the index a whole object or array can be skipped in one step, and a large
+//! top-level array can be split into element slices that parse
+//! independently.
+
+use crate::error::Error;
+use crate::tokenizer::Result;
+
+const QUOTE: u8 = 1;
+const BACKSLASH: u8 = 2;
+const STRUCTURAL: u8 = 4;
+
+static CLASSES: [u8; 256] = {
+    let mut classes = [0; 256];
+    classes[b'"' as usize] = QUOTE;
+    classes[b'\\' as usize] = BACKSLASH;
+    classes[b'{' as usize] = STRUCTURAL;
+    classes[b'}' as usize] = STRUCTURAL;
+    classes[b'[' as usize] = STRUCTURAL;
+    classes[b']' as usize] = STRUCTURAL;
+    classes[b':' as usize] = STRUCTURAL;
+    classes[b',' as usize] = STRUCTURAL;
+    classes
+};
+
+/// Marks a structural that is not a bracket in `partners`.
+const NO_PARTNER: usize = usize::MAX;
+
+pub struct StructuralIndex {
+    /// Byte offsets of the structural characters, in order.
+    positions: Vec<usize>,
+    /// For each bracket in `positions`, the index of its matching bracket.
+    partners: Vec<usize>,
+}
+
+impl StructuralIndex {
+    /// Indexes `input`. Fails if a string is unterminated or the brackets
+    /// do not balance; everything else is left to the tokenizer.
+    pub fn new(input: &str) -> Result<Self> {
+        let bytes = input.as_bytes();
+        let mut positions = Vec::new();
+        let mut escape_carry = 0u64;
+        let mut in_string_carry = 0u64;
+        for (block, chunk) in bytes.chunks(64).enumerate() {
+            let (quotes, backslashes, structurals) = classify(chunk);
+            let escaped = find_escaped(backslashes, &mut escape_carry);
+            let quotes = quotes & !escaped;
+            // Bits from an opening quote up to, not including, its closing
+            // quote; the carry continues a string from the previous block.
+            let in_string = prefix_xor(quotes) ^ in_string_carry;
+            in_string_carry = ((in_string as i64) >> 63) as u64;
+            let mut bits = structurals & !in_string;
+            while bits != 0 {
+                positions.push(block * 64 + bits.trailing_zeros() as usize);
+                bits &= bits - 1;
+            }
+        }
+        if in_string_carry != 0 {
+            let start = input.rfind('"').unwrap_or_default();
+            return Err(Error::UnterminatedString(start));
+        }
+        let partners = match_brackets(bytes, &positions)?;
+        Ok(StructuralIndex {
+            positions,
+            partners,
+        })
+    }
+
+    /// Byte offsets of the structural characters outside of strings.
+    pub fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    /// The byte offset of the bracket matching the one at byte `pos`.
+    pub fn matching(&self, pos: usize) -> Option<usize> {
+        let i = self.positions.binary_search(&pos).ok()?;
+        self.partner(i).map(|j| self.positions[j])
+    }
+
+    /// Splits the top-level array of `input` into its elements, without
+    /// looking inside them. `input` must be the document this index was
+    /// built from. Fails on an empty element, as in `[1,]`, and on anything
+    /// but whitespace after the array.
+    pub fn array_elements<'a>(&self, input: &'a str) -> Result<Vec<&'a str>> {
+        let open = input
+            .find(|c: char| !matches!(c, ' ' | '\t' | '\r' | '\n' | '\u{feff}'))
+            .unwrap_or(input.len());
+        if input.as_bytes().get(open) != Some(&b'[') {
+            return match input[open..].chars().next() {
+                Some(ch) => Err(Error::Unexpected(open, ch)),
+                None => Err(Error::EofWhileParsingValue(open)),
+            };
+        }
+        // The first structural is the opening bracket itself.
+        let close = self.partners[0];
+        let end = self.positions[close] + 1;
+        if let Some((pos, ch)) = input[end..]
+            .char_indices()
+            .find(|(_, c)| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        {
+            return Err(Error::Unexpected(end + pos, ch));
+        }
+        let mut elements = Vec::new();
+        let mut start = open + 1;
+        let mut i = 1;
+        while i < close {
+            match self.partner(i) {
+                Some(partner) => i = partner + 1,
+                None => {
+                    let pos = self.positions[i];
+                    if input.as_bytes()[pos] == b',' {
+                        let element = trim(&input[start..pos]);
+                        if element.is_empty() {
+                            return Err(Error::Unexpected(pos, ','));
+                        }
+                        elements.push(element);
+                        start = pos + 1;
+                    }
+                    i += 1;
+                }
+            }
+        }
+        let last = trim(&input[start..self.positions[close]]);
+        if !last.is_empty() {
+            elements.push(last);
+        } else if !elements.is_empty() {
+            return Err(Error::Unexpected(self.positions[close], ']'));
+        }
+        Ok(elements)
+    }
+
+    /// The first structural at or after byte `pos`, searching forward from
+    /// structural `from`; a binary search over the structurals after it.
+    pub(crate) fn seek(&self, from: usize, pos: usize) -> usize {
+        let from = from.min(self.positions.len());
+        from + self.positions[from..].partition_point(|&p| p < pos)
+    }
+
+    pub(crate) fn position(&self, i: usize) -> usize {
+        self.positions[i]
+    }
+
+    pub(crate) fn partner(&self, i: usize) -> Option<usize> {
+        match self.partners[i] {
+            NO_PARTNER => None,
+            j => Some(j),
+        }
+    }
+}
+
+/// Splits a top-level JSON array into the source text of its elements.
+pub fn split_array(input: &str) -> Result<Vec<&str>> {
+    StructuralIndex::new(input)?.array_elements(input)
+}
+
+fn trim(s: &str) -> &str {
+    s.trim_matches(|c| matches!(c, ' ' | '\t' | '\r' | '\n'))
+}
+
+/// Bitmasks of the quotes, backslashes and structural characters in a block
+/// of up to 64 bytes; bit `i` is byte `i`.
+fn classify(chunk: &[u8]) -> (u64, u64, u64) {
+    let (mut quotes, mut backslashes, mut structurals) = (0, 0, 0);
+    for (i, &b) in chunk.iter().enumerate() {
+        let class = CLASSES[b as usize] as u64;
+        quotes |= (class & QUOTE as u64) << i;
+        backslashes |= ((class & BACKSLASH as u64) >> 1) << i;
+        structurals |= ((class & STRUCTURAL as u64) >> 2) << i;
+    }
+    (quotes, backslashes, structurals)
+}
+
+/// The bytes escaped by a backslash. A backslash escapes the byte after it
+/// unless it is escaped itself; `carry` holds whether the first byte of the
+/// next block is escaped. Backslashes are rare, so walking them one by one
+/// is cheap.
+fn find_escaped(backslashes: u64, carry: &mut u64) -> u64 {
+    let mut escaped = *carry;
+    *carry = 0;
+    let mut bits = backslashes;
+    while bits != 0 {
+        let i = bits.trailing_zeros();
+        if escaped & (1 << i) == 0 {
+            if i == 63 {
+                *carry = 1;
+            } else {
+                escaped |= 1 << (i + 1);
+            }
+        }
+        bits &= bits - 1;
+    }
+    escaped
+}
+
+/// Bit `i` of the result is the XOR of bits `0..=i` of `x`.
+fn prefix_xor(mut x: u64) -> u64 {
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
+}
+
+fn match_brackets(bytes: &[u8], positions: &[usize]) -> Result<Vec<usize>> {
+    let mut partners = vec![NO_PARTNER; positions.len()];
+    let mut open = Vec::new();
+    for (i, &pos) in positions.iter().enumerate() {
+        match bytes[pos] {
+            b'{' | b'[' => open.push(i),
+            close @ (b'}' | b']') => {
+                let expected = if close == b'}' { b'{' } else { b'[' };
+                match open.pop() {
+                    Some(j) if bytes[positions[j]] == expected => {
+                        partners[i] = j;
+                        partners[j] = i;
+                    }
+                    _ => return Err(Error::Unexpected(pos, close as char)),
+                }
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(_) => Err(Error::EofWhileParsingValue(bytes.len())),
+        None => Ok(partners),
+    }
+}
+
+#[test]
+fn test_structural_index() {
+    let s = r#"{"a\"{": [1, {"b": "]"}], "c\\": {}}"#;
+    let index = StructuralIndex::new(s).unwrap();
+    let chars: String = index
+        .positions()
+        .iter()
+        .map(|&p| s.as_bytes()[p] as char)
+        .collect();
+    assert_eq!(chars, "{:[,{:}],:{}}");
+    assert_eq!(index.matching(0), Some(s.len() - 1));
+    assert_eq!(index.matching(9), s.find("],"));
+    assert_eq!(index.matching(1), None);
+
+    // A string spanning a block boundary, with an escaped quote on it.
+    let long = format!(r#"["{}\"]", 2]"#, "x".repeat(60));
+    assert_eq!(split_array(&long).unwrap(), [&long[1..66], "2"]);
+
+    assert_eq!(
+        split_array(" [1, [2, 3], {\"a\": [4]} ,\"x,y\"] ").unwrap(),
+        ["1", "[2, 3]", "{\"a\": [4]}", "\"x,y\""]
+    );
+    assert!(split_array("[]").unwrap().is_empty());
+    assert_eq!(split_array("[1, 2"), Err(Error::EofWhileParsingValue(5)));
+    assert_eq!(split_array("[1}"), Err(Error::Unexpected(2, '}')));
+    assert_eq!(split_array("[\"1]"), Err(Error::UnterminatedString(1)));
+    assert_eq!(split_array("[1,]"), Err(Error::Unexpected(3, ']')));
+    assert_eq!(split_array("[1,,2]"), Err(Error::Unexpected(3, ',')));
+    assert_eq!(split_array("[,]"), Err(Error::Unexpected(1, ',')));
+    assert_eq!(split_array("[1] x"), Err(Error::Unexpected(4, 'x')));
+    assert_eq!(split_array("[1] [2]"), Err(Error::Unexpected(4, '[')));
+    assert_eq!(split_array("[1]\n").unwrap(), ["1"]);
+}
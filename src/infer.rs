@@ -0,0 +1,327 @@
+//! JSON Schema inference from sample documents.
+//!
+//! An [`Inferrer`] merges the shapes of the documents it is given: the types
+//! seen at each position, which object members were missing from some
+//! samples, the shape of array elements, and whether all strings at a
+//! position share a format (`date-time`, `date`, `uuid` or `email`). Its
+//! [`schema`](Inferrer::schema) describes every sample it has seen.
+
+use crate::tokenizer::Result;
+use crate::value::{Map, Number, Value};
+
+/// Accumulates the shape of sample documents.
+#[derive(Debug, Default)]
+pub struct Inferrer {
+    pub(crate) root: Shape,
+}
+
+/// The values seen at one position.
+#[derive(Debug, Default)]
+pub(crate) struct Shape {
+    pub(crate) null: bool,
+    pub(crate) boolean: bool,
+    pub(crate) integer: bool,
+    /// Whether some integer was negative, and whether some was above
+    /// `i64::MAX`.
+    pub(crate) negative: bool,
+    pub(crate) huge: bool,
+    pub(crate) float: bool,
+    /// The format all strings so far share, if any; `None` if there were no
+    /// strings.
+    pub(crate) string: Option<Option<&'static str>>,
+    pub(crate) items: Option<Box<Shape>>,
+    pub(crate) object: Option<Object>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Object {
+    /// How many objects were seen.
+    pub(crate) count: usize,
+    /// Each member, with how many of the objects had it.
+    pub(crate) members: std::collections::BTreeMap<String, (usize, Shape)>,
+}
+
+impl Inferrer {
+    pub fn new() -> Self {
+        Inferrer::default()
+    }
+
+    pub fn add(&mut self, sample: &Value) {
+        self.root.add(sample);
+    }
+
+    /// Adds every sample from `samples`, such as a
+    /// [`StreamDeserializer`](crate::de::StreamDeserializer), and returns how
+    /// many there were. Stops at the first error.
+    pub fn add_all<I>(&mut self, samples: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = Result<Value>>,
+    {
+        let mut count = 0;
+        for sample in samples {
+            self.add(&sample?);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// A schema that every sample so far is valid against.
+    pub fn schema(&self) -> Value {
+        let mut document = Map::new();
+        document.insert(
+            "$schema".to_string(),
+            Value::String("https://json-schema.org/draft/2020-12/schema".to_string()),
+        );
+        if let Value::Object(root) = self.root.schema() {
+            document.extend(root);
+        }
+        Value::Object(document)
+    }
+
+    pub fn schema_string(&self) -> Result<String> {
+        crate::ser::to_string(&self.schema())
+    }
+}
+
+/// The schema inferred from the documents in `input`, one after another.
+pub fn infer(input: &str) -> Result<Value> {
+    let mut inferrer = Inferrer::new();
+    inferrer.add_all(crate::de::Deserializer::new(input).into_stream())?;
+    Ok(inferrer.schema())
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(Number::F64(_)) => self.float = true,
+            Value::Number(n) => {
+                self.integer = true;
+                match n.as_i64() {
+                    Some(n) => self.negative |= n < 0,
+                    None => self.huge = true,
+                }
+            }
+            Value::String(s) => {
+                let found = format(s);
+                self.string = match self.string {
+                    None => Some(found),
+                    Some(known) if known == found => Some(known),
+                    Some(_) => Some(None),
+                };
+            }
+            Value::Array(items) => {
+                let shape = self.items.get_or_insert_with(Box::default);
+                for item in items {
+                    shape.add(item);
+                }
+            }
+            Value::Object(map) => {
+                let object = self.object.get_or_insert_with(Object::default);
+                object.count += 1;
+                for (name, value) in map {
+                    let (count, shape) = object.members.entry(name.clone()).or_default();
+                    *count += 1;
+                    shape.add(value);
+                }
+            }
+        }
+    }
+
+    fn schema(&self) -> Value {
+        let mut types = Vec::new();
+        let mut schema = Map::new();
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        match (self.integer, self.float) {
+            (true, false) => types.push("integer"),
+            (_, true) => types.push("number"),
+            (false, false) => {}
+        }
+        if let Some(format) = self.string {
+            types.push("string");
+            if let Some(format) = format {
+                schema.insert("format".to_string(), Value::String(format.to_string()));
+            }
+        }
+        if let Some(items) = &self.items {
+            types.push("array");
+            // No elements at all: nothing is known about them.
+            if !items.is_empty() {
+                schema.insert("items".to_string(), items.schema());
+            }
+        }
+        if let Some(object) = &self.object {
+            types.push("object");
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for (name, (count, shape)) in &object.members {
+                properties.insert(name.clone(), shape.schema());
+                if *count == object.count {
+                    required.push(Value::String(name.clone()));
+                }
+            }
+            schema.insert("properties".to_string(), Value::Object(properties));
+            schema.insert("required".to_string(), Value::Array(required));
+        }
+        let types = match types.as_slice() {
+            [] => return Value::Object(schema),
+            [name] => Value::String(name.to_string()),
+            names => Value::Array(names.iter().map(|t| Value::String(t.to_string())).collect()),
+        };
+        schema.insert("type".to_string(), types);
+        Value::Object(schema)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.null
+            && !self.boolean
+            && !self.integer
+            && !self.float
+            && self.string.is_none()
+            && self.items.is_none()
+            && self.object.is_none()
+    }
+}
+
+/// The format `s` is in, of those inference knows.
+fn format(s: &str) -> Option<&'static str> {
+    if is_date_time(s) {
+        Some("date-time")
+    } else if is_date(s) {
+        Some("date")
+    } else if is_uuid(s) {
+        Some("uuid")
+    } else if is_email(s) {
+        Some("email")
+    } else {
+        None
+    }
+}
+
+/// Whether `digits` is all ASCII digits, read as a number in `range`.
+fn digits_in(digits: &[u8], range: std::ops::RangeInclusive<u32>) -> bool {
+    digits.iter().all(u8::is_ascii_digit)
+        && range.contains(&digits.iter().fold(0, |n, d| n * 10 + u32::from(d - b'0')))
+}
+
+/// A `full-date` of RFC 3339: `2024-02-29`.
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && digits_in(&b[..4], 0..=9999)
+        && digits_in(&b[5..7], 1..=12)
+        && digits_in(&b[8..], 1..=31)
+}
+
+/// A `date-time` of RFC 3339: `2024-02-29T12:00:00.5+01:00`.
+fn is_date_time(s: &str) -> bool {
+    let b = s.as_bytes();
+    if b.len() < 20 || !s.get(..10).is_some_and(is_date) || !matches!(b[10], b'T' | b't') {
+        return false;
+    }
+    let time = &b[11..];
+    if !(time[2] == b':'
+        && time[5] == b':'
+        && digits_in(&time[..2], 0..=23)
+        && digits_in(&time[3..5], 0..=59)
+        && digits_in(&time[6..8], 0..=60))
+    {
+        return false;
+    }
+    let mut rest = &time[8..];
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let len = fraction.iter().take_while(|d| d.is_ascii_digit()).count();
+        if len == 0 {
+            return false;
+        }
+        rest = &fraction[len..];
+    }
+    match rest {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => {
+            digits_in(&[*h1, *h2], 0..=23) && digits_in(&[*m1, *m2], 0..=59)
+        }
+        _ => false,
+    }
+}
+
+/// `8-4-4-4-12` hex digits.
+fn is_uuid(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 36
+        && b.iter().enumerate().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => *c == b'-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// A plain `local@domain.tld` address, without quoting or comments.
+fn is_email(s: &str) -> bool {
+    let (local, domain) = match s.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let plain =
+        |part: &str| !part.is_empty() && !part.contains(|c: char| c.is_whitespace() || c == '@');
+    plain(local)
+        && plain(domain)
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+#[test]
+fn test_infer() {
+    let samples = r#"
+        {"id": 1, "name": "a", "at": "2024-02-29T12:00:00Z", "tags": [], "score": 1}
+        {"id": 2, "name": null, "at": "2024-03-01t08:30:00.25+01:00", "tags": ["x"], "score": 2.5,
+         "owner": {"uuid": "123e4567-e89b-12d3-a456-426614174000", "email": "a@b.example"}}
+        {"id": 3, "name": "c", "at": "2024-03-02T00:00:00-05:00", "tags": ["y", 1], "score": null,
+         "owner": {"uuid": "123E4567-E89B-12D3-A456-426614174001", "email": "not an email"}}
+    "#;
+    let schema = infer(samples).unwrap();
+    let field = |name: &str| {
+        crate::ser::to_string(schema.pointer(&format!("/properties/{}", name)).unwrap()).unwrap()
+    };
+    assert_eq!(field("id"), r#"{"type":"integer"}"#);
+    assert_eq!(field("name"), r#"{"type":["null","string"]}"#);
+    assert_eq!(field("at"), r#"{"format":"date-time","type":"string"}"#);
+    assert_eq!(
+        field("tags"),
+        r#"{"items":{"type":["integer","string"]},"type":"array"}"#
+    );
+    assert_eq!(field("score"), r#"{"type":["null","number"]}"#);
+    assert_eq!(
+        field("owner"),
+        r#"{"properties":{"email":{"type":"string"},"uuid":{"format":"uuid","type":"string"}},"required":["email","uuid"],"type":"object"}"#
+    );
+    assert_eq!(
+        crate::ser::to_string(schema.pointer("/required").unwrap()).unwrap(),
+        r#"["at","id","name","score","tags"]"#
+    );
+
+    let validator = crate::schema::Validator::new(&schema).unwrap();
+    for sample in crate::de::Deserializer::new(samples).into_stream::<Value>() {
+        assert!(validator.is_valid(&sample.unwrap()));
+    }
+
+    let mut inferrer = Inferrer::new();
+    let added =
+        inferrer.add_all(crate::de::Deserializer::new(r#""2024-01-01" "a@b.co" []"#).into_stream());
+    assert_eq!(added, Ok(3));
+    assert_eq!(
+        inferrer.schema_string().unwrap(),
+        r#"{"$schema":"https://json-schema.org/draft/2020-12/schema","type":["string","array"]}"#
+    );
+    assert!(infer("{} {").is_err());
+    assert!(is_email("first.last@mail.example.org") && !is_email("a@b@c.d") && !is_email("a@b."));
+    assert!(is_date("2024-12-31") && !is_date("2024-13-01"));
+    assert!(!is_date_time("2024-01-01T24:00:00Z") && !is_date_time("2024-01-01T10:00:00"));
+}
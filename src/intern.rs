@@ -0,0 +1,216 @@
+//! Interning of object keys.
+//!
+//! Arrays of homogeneous objects repeat the same few keys thousands of
+//! times. A [`KeyInterner`] on the [`Deserializer`](crate::de::Deserializer)
+//! keeps one shared copy of each key, and map keys of type [`Key`] are handed
+//! those copies instead of fresh allocations. Keys with escapes are also
+//! remembered by their raw text, so a repeated escaped key, struct field
+//! names included, is not decoded again. The interner lives as long as the
+//! deserializer, so it is shared by every document of a
+//! [`StreamDeserializer`](crate::de::StreamDeserializer).
+
+use serde::{de, ser, Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// The newtype name by which [`Key`] asks this crate's deserializer for an
+/// interned key.
+pub(crate) const TOKEN: &str = "$json_rs::private::Key";
+
+thread_local! {
+    /// The key an [`Interned`] is visiting, set only for its one `visit_str`
+    /// call: visitors are handed text, not the `Arc` behind it.
+    static HANDOFF: Cell<Option<Arc<str>>> = const { Cell::new(None) };
+}
+
+/// A bounded set of keys. Once it holds `capacity` keys, new keys are still
+/// handed out but no longer remembered, so a document with unbounded
+/// distinct keys cannot grow it without limit.
+pub struct KeyInterner {
+    keys: HashSet<Arc<str>>,
+    /// Escaped keys by their raw text.
+    escaped: HashMap<Box<str>, Arc<str>>,
+    capacity: usize,
+}
+
+impl KeyInterner {
+    pub fn new(capacity: usize) -> Self {
+        KeyInterner {
+            keys: HashSet::new(),
+            escaped: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// The shared copy of `key`.
+    pub fn intern(&mut self, key: &str) -> Arc<str> {
+        if let Some(interned) = self.keys.get(key) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(key);
+        if self.keys.len() < self.capacity {
+            self.keys.insert(interned.clone());
+        }
+        interned
+    }
+
+    /// The shared copy of the key spelled `raw` with escapes, if seen.
+    pub(crate) fn get_escaped(&self, raw: &str) -> Option<Arc<str>> {
+        self.escaped.get(raw).cloned()
+    }
+
+    /// Interns `key`, remembering that `raw` decodes to it.
+    pub(crate) fn intern_escaped(&mut self, raw: &str, key: &str) -> Arc<str> {
+        let interned = self.intern(key);
+        if self.escaped.len() < self.capacity {
+            self.escaped.insert(raw.into(), interned.clone());
+        }
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl Default for KeyInterner {
+    fn default() -> Self {
+        KeyInterner::new(4096)
+    }
+}
+
+/// An interned key, passed by the deserializer as the content of the
+/// [`TOKEN`] newtype so that a [`Key`] can keep the shared copy.
+pub(crate) struct Interned(pub(crate) Arc<str>);
+
+impl<'de> de::Deserializer<'de> for Interned {
+    type Error = crate::error::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        HANDOFF.set(Some(self.0.clone()));
+        let result = visitor.visit_str(&self.0);
+        HANDOFF.take();
+        result
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// A string key that shares its text with every equal key deserialized
+/// through the same interner. Derefs and borrows as `str`, so a
+/// `HashMap<Key, V>` can be looked up with a `&str`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Key(Arc<str>);
+
+impl Key {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Arc<str> {
+        self.0
+    }
+}
+
+impl Deref for Key {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Key {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Key {
+    fn from(s: &str) -> Self {
+        Key(Arc::from(s))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> de::Visitor<'de> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Key, E>
+    where
+        E: de::Error,
+    {
+        match HANDOFF.take() {
+            Some(interned) if &*interned == v => Ok(Key(interned)),
+            _ => Ok(Key::from(v)),
+        }
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Key, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+#[test]
+fn test_key_interner() {
+    let mut interner = KeyInterner::new(2);
+    let a = interner.intern("a");
+    assert!(Arc::ptr_eq(&a, &interner.intern("a")));
+    interner.intern("b");
+    // Full: still handed out, but not shared.
+    assert!(!Arc::ptr_eq(&interner.intern("c"), &interner.intern("c")));
+    assert_eq!(interner.len(), 2);
+
+    let key = Key::deserialize(Interned(a.clone())).unwrap();
+    assert!(Arc::ptr_eq(&a, &key.into_inner()));
+    // The handoff does not outlive the call.
+    let key = de::Visitor::visit_str::<crate::error::Error>(KeyVisitor, "a").unwrap();
+    assert!(!Arc::ptr_eq(&a, &key.into_inner()));
+}
@@ -1,9 +1,25 @@
+pub mod codegen;
 pub mod de;
+pub mod diff;
 pub mod ser;
 #[macro_use]
 pub mod error;
+mod float;
+pub mod index;
+pub mod infer;
+pub mod intern;
+pub mod merge;
+pub mod patch;
+pub mod path;
+pub mod pointer;
+mod regex;
+mod scan;
+pub mod schema;
+pub mod stream;
 mod token;
 pub mod tokenizer;
+pub mod trace;
+pub mod value;
 
 pub fn decompress_rl_elist(nums: Vec<i32>) -> Vec<i32> {
     let mut ans: Vec<i32> = Vec::new();
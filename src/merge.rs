@@ -0,0 +1,144 @@
+//! JSON Merge Patch (RFC 7396).
+//!
+//! A merge patch looks like the document it changes: members set to `null`
+//! are removed, objects are merged member by member, and anything else
+//! replaces what was there.
+
+use crate::de::DuplicateKeys;
+use crate::tokenizer::Result;
+use crate::value::{Map, Value};
+
+/// Applies the merge patch `patch` to `doc`.
+pub fn apply(doc: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *doc = patch.clone();
+            return;
+        }
+    };
+    if !matches!(doc, Value::Object(_)) {
+        *doc = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = doc {
+        for (key, value) in patch {
+            match value {
+                Value::Null => {
+                    map.remove(key);
+                }
+                value => apply(map.entry(key.clone()).or_default(), value),
+            }
+        }
+    }
+}
+
+/// The smallest merge patch that turns `from` into `to`. A merge patch
+/// cannot set a member to `null`, so `null` members of `to` are treated as
+/// absent.
+pub fn diff(from: &Value, to: &Value) -> Value {
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => Value::Object(diff_members(from, to)),
+        (_, to) => to.clone(),
+    }
+}
+
+fn diff_members(from: &Map, to: &Map) -> Map {
+    let mut patch = Map::new();
+    for key in from.keys() {
+        if !to.get(key).is_some_and(|value| *value != Value::Null) {
+            patch.insert(key.clone(), Value::Null);
+        }
+    }
+    for (key, value) in to {
+        match (from.get(key), value) {
+            (_, Value::Null) => {}
+            (Some(old), value) if old.same_as(value) => {}
+            (Some(Value::Object(old)), Value::Object(new)) => {
+                // Empty when the objects differ only in `null` members.
+                let nested = diff_members(old, new);
+                if !nested.is_empty() {
+                    patch.insert(key.clone(), Value::Object(nested));
+                }
+            }
+            (_, value) => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    patch
+}
+
+/// Applies the merge patch text `patch` to the document text `doc`, and
+/// returns the patched document.
+pub fn apply_str(doc: &str, patch: &str) -> Result<String> {
+    apply_str_with(doc, patch, DuplicateKeys::Allow)
+}
+
+/// Like [`apply_str`], reading objects with a repeated key in either text
+/// by `policy`.
+pub fn apply_str_with(doc: &str, patch: &str, policy: DuplicateKeys) -> Result<String> {
+    let mut doc: Value = crate::de::from_str_with(doc, policy)?;
+    apply(&mut doc, &crate::de::from_str_with(patch, policy)?);
+    crate::ser::to_string(&doc)
+}
+
+/// The text of the smallest merge patch that turns `from` into `to`.
+pub fn diff_str(from: &str, to: &str) -> Result<String> {
+    diff_str_with(from, to, DuplicateKeys::Allow)
+}
+
+/// Like [`diff_str`], reading objects with a repeated key in either text
+/// by `policy`.
+pub fn diff_str_with(from: &str, to: &str, policy: DuplicateKeys) -> Result<String> {
+    let from: Value = crate::de::from_str_with(from, policy)?;
+    let to: Value = crate::de::from_str_with(to, policy)?;
+    crate::ser::to_string(&diff(&from, &to))
+}
+
+#[test]
+fn test_merge_patch() {
+    let doc = r#"{"a": "b", "c": {"d": "e", "f": "g"}, "h": [1]}"#;
+    assert_eq!(
+        apply_str(doc, r#"{"a": "z", "c": {"f": null}, "h": {"i": null}}"#).unwrap(),
+        r#"{"a":"z","c":{"d":"e"},"h":{}}"#
+    );
+    assert_eq!(apply_str(doc, "[1]").unwrap(), "[1]");
+    assert_eq!(
+        apply_str("[1]", r#"{"a": {"b": 1}}"#).unwrap(),
+        r#"{"a":{"b":1}}"#
+    );
+
+    let to = r#"{"a": "b", "c": {"d": "x"}, "h": [1, 2], "n": null, "k": 1.0}"#;
+    let patch = diff_str(doc, to).unwrap();
+    assert_eq!(patch, r#"{"c":{"d":"x","f":null},"h":[1,2],"k":1.0}"#);
+    let patched: Value = crate::de::from_str(&apply_str(doc, &patch).unwrap()).unwrap();
+    let mut expected: Value = crate::de::from_str(to).unwrap();
+    apply(
+        &mut expected,
+        &crate::de::from_str(r#"{"n": null}"#).unwrap(),
+    );
+    assert_eq!(patched, expected);
+
+    assert_eq!(diff_str(doc, doc).unwrap(), "{}");
+    assert_eq!(diff_str("1", "1.0").unwrap(), "1.0");
+    assert_eq!(
+        diff_str(r#"{"a": {}}"#, r#"{"a": {"x": null}}"#).unwrap(),
+        "{}"
+    );
+    assert_eq!(
+        diff_str(
+            r#"{"a": {"b": {}}}"#,
+            r#"{"a": {"b": {"x": null}, "c": 1}}"#
+        )
+        .unwrap(),
+        r#"{"a":{"c":1}}"#
+    );
+
+    let twice = r#"{"a": 1, "a": 2}"#;
+    assert_eq!(apply_str("{}", twice).unwrap(), r#"{"a":2}"#);
+    assert_eq!(
+        apply_str_with("{}", twice, DuplicateKeys::FirstWins).unwrap(),
+        r#"{"a":1}"#
+    );
+    assert!(diff_str_with(twice, "{}", DuplicateKeys::Error).is_err());
+}
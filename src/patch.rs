@@ -0,0 +1,215 @@
+//! JSON Patch (RFC 6902).
+//!
+//! A patch is parsed with [`Deserializer`](crate::de::Deserializer) into
+//! [`Operation`]s and applied to a [`Value`]. Application is atomic: the
+//! operations run on a copy, which replaces the document only if all of them
+//! succeed.
+
+use crate::de::DuplicateKeys;
+use crate::error::Error;
+use crate::pointer;
+use crate::tokenizer::Result;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Parses a patch document.
+pub fn parse(patch: &str) -> Result<Vec<Operation>> {
+    parse_with(patch, DuplicateKeys::Allow)
+}
+
+/// Like [`parse`], reading objects with a repeated key by `policy`.
+pub fn parse_with(patch: &str, policy: DuplicateKeys) -> Result<Vec<Operation>> {
+    crate::de::from_str_with(patch, policy)
+}
+
+/// Applies `patch` to `doc`. On failure `doc` is left as it was, and the
+/// error names the index of the failing operation.
+pub fn apply(doc: &mut Value, patch: &[Operation]) -> Result<()> {
+    let mut patched = doc.clone();
+    for (i, op) in patch.iter().enumerate() {
+        apply_one(&mut patched, op).map_err(|reason| Error::PatchFailed(i, reason))?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+/// Applies the patch text `patch` to the document text `doc`, and returns
+/// the patched document.
+pub fn apply_str(doc: &str, patch: &str) -> Result<String> {
+    apply_str_with(doc, patch, DuplicateKeys::Allow)
+}
+
+/// Like [`apply_str`], reading objects with a repeated key in either text
+/// by `policy`.
+pub fn apply_str_with(doc: &str, patch: &str, policy: DuplicateKeys) -> Result<String> {
+    let mut doc: Value = crate::de::from_str_with(doc, policy)?;
+    apply(&mut doc, &parse_with(patch, policy)?)?;
+    crate::ser::to_string(&doc)
+}
+
+fn apply_one(doc: &mut Value, op: &Operation) -> std::result::Result<(), String> {
+    match op {
+        Operation::Add { path, value } => add(doc, path, value.clone()),
+        Operation::Remove { path } => remove(doc, path).map(drop),
+        Operation::Replace { path, value } => {
+            let target = doc
+                .pointer_mut(path)
+                .ok_or_else(|| format!("no value at {:?}", path))?;
+            *target = value.clone();
+            Ok(())
+        }
+        Operation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(format!("cannot move {:?} into itself", from));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        Operation::Copy { from, path } => {
+            let value = doc
+                .pointer(from)
+                .ok_or_else(|| format!("no value at {:?}", from))?
+                .clone();
+            add(doc, path, value)
+        }
+        Operation::Test { path, value } => match doc.pointer(path) {
+            Some(found) if found.same_as(value) => Ok(()),
+            Some(_) => Err(format!("test failed at {:?}", path)),
+            None => Err(format!("no value at {:?}", path)),
+        },
+    }
+}
+
+/// The container that holds the target of `path`, and the target's
+/// reference token. `None` for the empty pointer, the whole document.
+fn parent<'v>(
+    doc: &'v mut Value,
+    path: &str,
+) -> std::result::Result<Option<(&'v mut Value, String)>, String> {
+    let mut tokens = pointer::parse(path).map_err(|_| format!("invalid pointer {:?}", path))?;
+    let last = match tokens.pop() {
+        Some(last) => last,
+        None => return Ok(None),
+    };
+    let parent_path: String = tokens
+        .iter()
+        .map(|token| format!("/{}", pointer::escape(token)))
+        .collect();
+    match doc.pointer_mut(&parent_path) {
+        Some(parent) => Ok(Some((parent, last))),
+        None => Err(format!("no value at {:?}", parent_path)),
+    }
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> std::result::Result<(), String> {
+    match parent(doc, path)? {
+        None => *doc = value,
+        Some((Value::Object(map), key)) => {
+            map.insert(key, value);
+        }
+        Some((Value::Array(items), token)) => {
+            let index = match token.as_str() {
+                "-" => items.len(),
+                token => match pointer::array_index(token) {
+                    Some(index) if index <= items.len() => index,
+                    _ => return Err(format!("invalid array index in {:?}", path)),
+                },
+            };
+            items.insert(index, value);
+        }
+        Some(_) => return Err(format!("cannot add to a scalar at {:?}", path)),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> std::result::Result<Value, String> {
+    let removed = match parent(doc, path)? {
+        None => return Err("cannot remove the whole document".to_string()),
+        Some((Value::Object(map), key)) => map.remove(&key),
+        Some((Value::Array(items), token)) => match pointer::array_index(&token) {
+            Some(index) if index < items.len() => Some(items.remove(index)),
+            _ => None,
+        },
+        Some(_) => None,
+    };
+    removed.ok_or_else(|| format!("no value at {:?}", path))
+}
+
+#[test]
+fn test_patch() {
+    let doc = r#"{"a": {"b": [1, 2]}, "c": "x", "n": 1.0}"#;
+    let patched = apply_str(
+        doc,
+        r#"[
+            {"op": "test", "path": "/n", "value": 1},
+            {"op": "add", "path": "/a/b/1", "value": {"d": null}},
+            {"op": "add", "path": "/a/b/-", "value": 3},
+            {"op": "remove", "path": "/a/b/0"},
+            {"op": "replace", "path": "/c", "value": "say \"hi\""},
+            {"op": "copy", "from": "/c", "path": "/e~1f"},
+            {"op": "move", "from": "/a/b", "path": "/g"}
+        ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        patched,
+        r#"{"a":{},"c":"say \"hi\"","e/f":"say \"hi\"","g":[{"d":null},2,3],"n":1.0}"#
+    );
+
+    let mut value: Value = crate::de::from_str(doc).unwrap();
+    let before = value.clone();
+    let patch = parse(
+        r#"[
+            {"op": "remove", "path": "/c"},
+            {"op": "move", "from": "/a", "path": "/a/b/x"}
+        ]"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        apply(&mut value, &patch),
+        Err(Error::PatchFailed(1, _))
+    ));
+    assert_eq!(value, before);
+
+    let failing = |patch: &str| match apply_str(doc, patch) {
+        Err(Error::PatchFailed(i, _)) => i,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(
+        failing(r#"[{"op": "test", "path": "/c", "value": "y"}]"#),
+        0
+    );
+    assert_eq!(
+        failing(r#"[{"op": "add", "path": "/a/b/3", "value": 0}]"#),
+        0
+    );
+    assert_eq!(
+        failing(r#"[{"op": "remove", "path": "/c"}, {"op": "replace", "path": "/c", "value": 0}]"#),
+        1
+    );
+    assert_eq!(failing(r#"[{"op": "add", "path": "/x/y", "value": 0}]"#), 0);
+    assert!(parse(r#"[{"op": "frobnicate", "path": ""}]"#).is_err());
+    assert_eq!(
+        apply_str(doc, r#"[{"op": "add", "path": "", "value": [true]}]"#).unwrap(),
+        "[true]"
+    );
+
+    let twice = r#"[{"op": "add", "path": "/a", "value": 1, "value": 2}]"#;
+    assert!(parse(twice).is_err());
+    assert_eq!(
+        apply_str_with("{}", twice, DuplicateKeys::LastWins).unwrap(),
+        r#"{"a":2}"#
+    );
+    assert!(apply_str_with(r#"{"a": 1, "a": 2}"#, "[]", DuplicateKeys::Error).is_err());
+}
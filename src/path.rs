@@ -0,0 +1,815 @@
+//! JSONPath (RFC 9535) queries over a parsed [`Value`].
+//!
+//! Supports name, wildcard, index, slice and filter selectors, child and
+//! descendant segments, and filters built from existence tests, comparisons,
+//! `!`, `&&`, `||` and parentheses. Function extensions such as `length()`
+//! are not supported. Every match comes with its normalized path, such as
+//! `$['orders'][0]['id']`.
+//!
+//! [`Value::Object`] keeps its members sorted by key, so results come in
+//! value order: array elements by index and object members by key, not in
+//! the order they were written in the source text.
+//!
+//! Queries run over a [`Value`] the caller has parsed, so a document with
+//! repeated keys is read by whatever policy it was parsed with, such as
+//! [`crate::de::from_str_with`] and a [`crate::de::DuplicateKeys`].
+
+use crate::error::Error;
+use crate::tokenizer::Result;
+use crate::value::{Number, Value};
+
+/// A compiled JSONPath query.
+#[derive(Debug)]
+pub struct Path {
+    query: Query,
+}
+
+/// One node a query selected.
+#[derive(Debug, PartialEq)]
+pub struct Node<'v> {
+    /// The normalized path of the node.
+    pub path: String,
+    pub value: &'v Value,
+}
+
+#[derive(Debug)]
+struct Query {
+    /// Starts at `@`, the node a filter is looking at, rather than `$`.
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug)]
+struct Segment {
+    /// `..`: the selectors apply to the node and all its descendants.
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Box<Filter>),
+}
+
+#[derive(Debug)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Not(Box<Filter>),
+    Exists(Query),
+    Compare(Comparable, Op, Comparable),
+}
+
+#[derive(Debug)]
+enum Comparable {
+    Literal(Value),
+    /// A query that selects at most one node.
+    Query(Query),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// The largest integer RFC 9535 allows in an index or slice.
+const MAX_INT: i64 = (1 << 53) - 1;
+
+impl Path {
+    pub fn parse(path: &str) -> Result<Path> {
+        let mut parser = Parser {
+            bytes: path.as_bytes(),
+            pos: 0,
+        };
+        if !parser.eat(b'$') {
+            return Err(parser.error());
+        }
+        let query = parser.query(false)?;
+        if parser.pos != path.len() {
+            return Err(parser.error());
+        }
+        Ok(Path { query })
+    }
+
+    /// The nodes of `root` the query selects, in value order: elements by
+    /// index, members by key.
+    pub fn select<'v>(&self, root: &'v Value) -> Vec<Node<'v>> {
+        self.query
+            .eval(root, root)
+            .into_iter()
+            .map(|(path, value)| Node { path, value })
+            .collect()
+    }
+}
+
+/// Parses `path` and selects its nodes from `root`.
+pub fn select<'v>(root: &'v Value, path: &str) -> Result<Vec<Node<'v>>> {
+    Ok(Path::parse(path)?.select(root))
+}
+
+struct Parser<'s> {
+    bytes: &'s [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> Error {
+        Error::InvalidPath(self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.bytes[self.pos..].starts_with(s.as_bytes()) {
+            self.pos += s.len();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, b: u8) -> Result<()> {
+        match self.eat(b) {
+            true => Ok(()),
+            false => Err(self.error()),
+        }
+    }
+
+    fn blank(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// The segments after a `$` or `@`.
+    fn query(&mut self, relative: bool) -> Result<Query> {
+        let mut segments = Vec::new();
+        loop {
+            let before = self.pos;
+            self.blank();
+            match self.peek() {
+                Some(b'.' | b'[') => segments.push(self.segment()?),
+                _ => {
+                    self.pos = before;
+                    return Ok(Query { relative, segments });
+                }
+            }
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment> {
+        if self.eat(b'[') {
+            return self.bracketed(false);
+        }
+        self.expect(b'.')?;
+        let descendant = self.eat(b'.');
+        let selector = match self.peek() {
+            Some(b'[') if descendant => {
+                self.pos += 1;
+                return self.bracketed(true);
+            }
+            Some(b'*') => {
+                self.pos += 1;
+                Selector::Wildcard
+            }
+            _ => Selector::Name(self.shorthand()?),
+        };
+        Ok(Segment {
+            descendant,
+            selectors: vec![selector],
+        })
+    }
+
+    /// A member name after `.`: a letter, `_` or non-ASCII char, then those
+    /// or digits.
+    fn shorthand(&mut self) -> Result<String> {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            let first = self.pos == start;
+            if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 || (!first && b.is_ascii_digit()) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(self.error());
+        }
+        // Stops only at ASCII bytes, so this is a whole number of chars.
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned())
+    }
+
+    /// Selectors after a `[`, up to and including the `]`.
+    fn bracketed(&mut self, descendant: bool) -> Result<Segment> {
+        let mut selectors = Vec::new();
+        loop {
+            self.blank();
+            selectors.push(self.selector()?);
+            self.blank();
+            if self.eat(b']') {
+                return Ok(Segment {
+                    descendant,
+                    selectors,
+                });
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some(b'\'' | b'"') => Ok(Selector::Name(self.string()?)),
+            Some(b'*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                self.blank();
+                Ok(Selector::Filter(Box::new(self.or()?)))
+            }
+            _ => {
+                let start = self.int()?;
+                self.blank();
+                if !self.eat(b':') {
+                    return start.map(Selector::Index).ok_or_else(|| self.error());
+                }
+                self.blank();
+                let end = self.int()?;
+                self.blank();
+                let step = match self.eat(b':') {
+                    true => {
+                        self.blank();
+                        self.int()?
+                    }
+                    false => None,
+                };
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    /// An optional integer: `0`, or an optional `-` and digits without a
+    /// leading zero.
+    fn int(&mut self) -> Result<Option<i64>> {
+        let start = self.pos;
+        self.eat(b'-');
+        match self.peek() {
+            Some(b'0') if self.pos == start => {
+                self.pos += 1;
+                return Ok(Some(0));
+            }
+            Some(b'1'..=b'9') => {}
+            _ if self.pos == start => return Ok(None),
+            _ => return Err(self.error()),
+        }
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        match text.parse::<i64>() {
+            Ok(n) if (-MAX_INT..=MAX_INT).contains(&n) => Ok(Some(n)),
+            _ => Err(Error::InvalidPath(start)),
+        }
+    }
+
+    /// A string literal in single or double quotes.
+    fn string(&mut self) -> Result<String> {
+        let quote = self.bytes[self.pos];
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    // Only ASCII is added or removed, so this stays UTF-8.
+                    return Ok(String::from_utf8_lossy(&out).into_owned());
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let ch = match self.peek() {
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b @ (b'/' | b'\\' | b'\'' | b'"')) => b as char,
+                        Some(b'u') => {
+                            self.pos += 1;
+                            let ch = self.unicode_escape()?;
+                            out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                            continue;
+                        }
+                        _ => return Err(self.error()),
+                    };
+                    self.pos += 1;
+                    out.push(ch as u8);
+                }
+                Some(b) if b >= 0x20 => {
+                    self.pos += 1;
+                    out.push(b);
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    /// The hex digits of a `\u` escape, and of a second one if the first is
+    /// a high surrogate.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat_str("\\u") {
+                return Err(self.error());
+            }
+            match self.hex4()? {
+                low @ 0xDC00..=0xDFFF => 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00),
+                _ => return Err(self.error()),
+            }
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error())
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error())?;
+        let digits = std::str::from_utf8(digits).map_err(|_| self.error())?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error())?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut terms = vec![self.and()?];
+        loop {
+            let before = self.pos;
+            self.blank();
+            if !self.eat_str("||") {
+                self.pos = before;
+                break;
+            }
+            self.blank();
+            terms.push(self.and()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Filter::Or(terms),
+        })
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut terms = vec![self.basic()?];
+        loop {
+            let before = self.pos;
+            self.blank();
+            if !self.eat_str("&&") {
+                self.pos = before;
+                break;
+            }
+            self.blank();
+            terms.push(self.basic()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Filter::And(terms),
+        })
+    }
+
+    fn basic(&mut self) -> Result<Filter> {
+        if self.eat(b'!') {
+            self.blank();
+            let negated = match self.peek() {
+                Some(b'(') => self.paren()?,
+                _ => Filter::Exists(self.filter_query()?.ok_or_else(|| self.error())?),
+            };
+            return Ok(Filter::Not(Box::new(negated)));
+        }
+        if self.peek() == Some(b'(') {
+            return self.paren();
+        }
+        let left = match self.filter_query()? {
+            Some(query) => {
+                let before = self.pos;
+                self.blank();
+                if !matches!(self.peek(), Some(b'=' | b'!' | b'<' | b'>')) {
+                    self.pos = before;
+                    return Ok(Filter::Exists(query));
+                }
+                self.pos = before;
+                self.singular(query)?
+            }
+            None => Comparable::Literal(self.literal()?),
+        };
+        self.blank();
+        let op = self.op()?;
+        self.blank();
+        let right = match self.filter_query()? {
+            Some(query) => self.singular(query)?,
+            None => Comparable::Literal(self.literal()?),
+        };
+        Ok(Filter::Compare(left, op, right))
+    }
+
+    fn paren(&mut self) -> Result<Filter> {
+        self.expect(b'(')?;
+        self.blank();
+        let filter = self.or()?;
+        self.blank();
+        self.expect(b')')?;
+        Ok(filter)
+    }
+
+    /// A query starting at `@` or `$`, if one comes next.
+    fn filter_query(&mut self) -> Result<Option<Query>> {
+        match self.peek() {
+            Some(b'@') => {
+                self.pos += 1;
+                self.query(true).map(Some)
+            }
+            Some(b'$') => {
+                self.pos += 1;
+                self.query(false).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Only queries that select at most one node can be compared.
+    fn singular(&self, query: Query) -> Result<Comparable> {
+        let singular = query.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors[..],
+                    [Selector::Name(_)] | [Selector::Index(_)]
+                )
+        });
+        match singular {
+            true => Ok(Comparable::Query(query)),
+            false => Err(self.error()),
+        }
+    }
+
+    fn op(&mut self) -> Result<Op> {
+        for (text, op) in [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if self.eat_str(text) {
+                return Ok(op);
+            }
+        }
+        Err(self.error())
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        if self.eat_str("true") {
+            return Ok(Value::Bool(true));
+        }
+        if self.eat_str("false") {
+            return Ok(Value::Bool(false));
+        }
+        if self.eat_str("null") {
+            return Ok(Value::Null);
+        }
+        match self.peek() {
+            Some(b'\'' | b'"') => Ok(Value::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let rest = std::str::from_utf8(&self.bytes[start..]).map_err(|_| self.error())?;
+        let (number, len) = crate::float::scan(rest).map_err(|_| self.error())?;
+        self.pos += len;
+        let number = match number {
+            crate::token::ParseNumber::U64(n) => Number::U64(n),
+            crate::token::ParseNumber::I64(n) => Number::I64(n),
+            number => Number::F64(number.to_f64()?),
+        };
+        Ok(Value::Number(number))
+    }
+}
+
+impl Query {
+    /// The selected nodes with their normalized paths. `current` is the
+    /// node `@` refers to.
+    fn eval<'v>(&self, root: &'v Value, current: &'v Value) -> Vec<(String, &'v Value)> {
+        let mut nodes = match self.relative {
+            true => vec![("@".to_string(), current)],
+            false => vec![("$".to_string(), root)],
+        };
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for (path, value) in &nodes {
+                match segment.descendant {
+                    true => segment.descend(path, value, root, &mut next),
+                    false => segment.apply(path, value, root, &mut next),
+                }
+            }
+            nodes = next;
+        }
+        nodes
+    }
+}
+
+impl Segment {
+    fn apply<'v>(
+        &self,
+        path: &str,
+        value: &'v Value,
+        root: &'v Value,
+        out: &mut Vec<(String, &'v Value)>,
+    ) {
+        for selector in &self.selectors {
+            selector.apply(path, value, root, out);
+        }
+    }
+
+    /// Applies the selectors to `value`, then to its descendants in value
+    /// order.
+    fn descend<'v>(
+        &self,
+        path: &str,
+        value: &'v Value,
+        root: &'v Value,
+        out: &mut Vec<(String, &'v Value)>,
+    ) {
+        self.apply(path, value, root, out);
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.descend(&index_path(path, i), item, root, out);
+                }
+            }
+            Value::Object(map) => {
+                for (key, member) in map {
+                    self.descend(&name_path(path, key), member, root, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Selector {
+    fn apply<'v>(
+        &self,
+        path: &str,
+        value: &'v Value,
+        root: &'v Value,
+        out: &mut Vec<(String, &'v Value)>,
+    ) {
+        match (self, value) {
+            (Selector::Name(name), Value::Object(map)) => {
+                if let Some(member) = map.get(name) {
+                    out.push((name_path(path, name), member));
+                }
+            }
+            (Selector::Wildcard, _) => children(path, value, |child| out.push(child)),
+            (Selector::Index(i), Value::Array(items)) => {
+                let len = items.len() as i64;
+                let i = if *i < 0 { len + i } else { *i };
+                if (0..len).contains(&i) {
+                    out.push((index_path(path, i as usize), &items[i as usize]));
+                }
+            }
+            (Selector::Slice(start, end, step), Value::Array(items)) => {
+                for i in slice_indices(items.len() as i64, *start, *end, *step) {
+                    out.push((index_path(path, i), &items[i]));
+                }
+            }
+            (Selector::Filter(filter), _) => children(path, value, |(child_path, child)| {
+                if filter.test(root, child) {
+                    out.push((child_path, child));
+                }
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// The children of `value` with their paths: elements by index, members by
+/// key.
+fn children<'v>(path: &str, value: &'v Value, mut f: impl FnMut((String, &'v Value))) {
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                f((index_path(path, i), item));
+            }
+        }
+        Value::Object(map) => {
+            for (key, member) in map {
+                f((name_path(path, key), member));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The indices a slice selects from an array of `len`, per RFC 9535.
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+impl Filter {
+    fn test(&self, root: &Value, current: &Value) -> bool {
+        match self {
+            Filter::Or(terms) => terms.iter().any(|t| t.test(root, current)),
+            Filter::And(terms) => terms.iter().all(|t| t.test(root, current)),
+            Filter::Not(filter) => !filter.test(root, current),
+            Filter::Exists(query) => !query.eval(root, current).is_empty(),
+            Filter::Compare(left, op, right) => {
+                let left = left.eval(root, current);
+                let right = right.eval(root, current);
+                match op {
+                    Op::Eq => equal(left, right),
+                    Op::Ne => !equal(left, right),
+                    Op::Lt => less(left, right),
+                    Op::Le => less(left, right) || equal(left, right),
+                    Op::Gt => less(right, left),
+                    Op::Ge => less(right, left) || equal(left, right),
+                }
+            }
+        }
+    }
+}
+
+impl Comparable {
+    /// The value compared, or `None` if a query selected nothing.
+    fn eval<'v>(&'v self, root: &'v Value, current: &'v Value) -> Option<&'v Value> {
+        match self {
+            Comparable::Literal(value) => Some(value),
+            Comparable::Query(query) => query.eval(root, current).pop().map(|(_, v)| v),
+        }
+    }
+}
+
+/// `==` of RFC 9535: two empty results are equal, and numbers compare by
+/// value whatever their spelling.
+fn equal(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.same_as(b),
+        _ => false,
+    }
+}
+
+/// `<` of RFC 9535: only numbers and strings are ordered.
+fn less(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            a.compare(*b) == Some(std::cmp::Ordering::Less)
+        }
+        (Some(Value::String(a)), Some(Value::String(b))) => a < b,
+        _ => false,
+    }
+}
+
+fn index_path(path: &str, i: usize) -> String {
+    format!("{}[{}]", path, i)
+}
+
+/// Appends a name in the normalized form: single quotes, with `'`, `\` and
+/// control chars escaped.
+fn name_path(path: &str, name: &str) -> String {
+    let mut out = String::with_capacity(path.len() + name.len() + 4);
+    out.push_str(path);
+    out.push_str("['");
+    for ch in name.chars() {
+        match ch {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch < ' ' => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push_str("']");
+    out
+}
+
+#[test]
+fn test_path() {
+    use crate::de::from_str;
+
+    let doc: Value = from_str(
+        r#"{"orders": [
+            {"id": 1, "total": 50, "tags": ["a"]},
+            {"id": 2, "total": 150.5, "vip": true},
+            {"id": 3, "total": 100, "note": "it's"}
+        ], "limit": 100, "o'k": {"id": 9}}"#,
+    )
+    .unwrap();
+    let paths = |path: &str| -> Vec<String> {
+        select(&doc, path)
+            .unwrap()
+            .into_iter()
+            .map(|node| node.path)
+            .collect()
+    };
+    let values = |path: &str| -> Vec<Value> {
+        select(&doc, path)
+            .unwrap()
+            .into_iter()
+            .map(|node| node.value.clone())
+            .collect()
+    };
+    let num = |n: u64| Value::Number(Number::U64(n));
+
+    assert_eq!(values("$.orders[?(@.total > 100)].id"), [num(2)]);
+    let big: Value = from_str(r#"[9007199254740993, 18446744073709551615]"#).unwrap();
+    let found = select(&big, "$[?@ > 9007199254740992.0]").unwrap();
+    assert_eq!(found.len(), 2);
+    // Members come out by key, not as written.
+    assert_eq!(
+        paths("$.orders[0].*")[..2],
+        ["$['orders'][0]['id']", "$['orders'][0]['tags']"]
+    );
+    assert_eq!(values("$.orders[?@.total >= $.limit].id"), [num(2), num(3)]);
+    assert_eq!(
+        paths("$.orders[?@.vip || @.total < 60]"),
+        ["$['orders'][0]", "$['orders'][1]"]
+    );
+    assert_eq!(values("$.orders[?!@.tags && @.total == 1e2].id"), [num(3)]);
+    assert_eq!(values("$.orders[?@.note == 'it\\'s'].id"), [num(3)]);
+    assert_eq!(values("$.orders[?@.missing == $.absent].id").len(), 3);
+
+    assert_eq!(values("$.orders[-1].id"), [num(3)]);
+    assert_eq!(values("$.orders[::-2].id"), [num(3), num(1)]);
+    assert_eq!(values("$.orders[1:].id"), [num(2), num(3)]);
+    assert_eq!(values("$.orders[0, 2]['id']"), [num(1), num(3)]);
+    assert_eq!(values("$.orders[0].*").len(), 3);
+    assert_eq!(
+        paths("$..id"),
+        [
+            "$['o\\'k']['id']",
+            "$['orders'][0]['id']",
+            "$['orders'][1]['id']",
+            "$['orders'][2]['id']"
+        ]
+    );
+    assert_eq!(
+        paths("$..[0]"),
+        ["$['orders'][0]", "$['orders'][0]['tags'][0]"]
+    );
+
+    assert_eq!(Path::parse("orders").unwrap_err(), Error::InvalidPath(0));
+    assert_eq!(
+        Path::parse("$.orders[01]").unwrap_err(),
+        Error::InvalidPath(10)
+    );
+    assert!(Path::parse("$[?@..id == 1]").is_err());
+    assert!(Path::parse("$.a b").is_err());
+}
@@ -0,0 +1,172 @@
+//! JSON Pointer (RFC 6901) lookups straight on the input text.
+//!
+//! The document is walked with the tokenizer: members and elements before
+//! the one a reference token names are skipped token by token without being
+//! built, and nothing after the target is looked at, so the cost is a scan up
+//! to the target.
+
+use crate::de::Deserializer;
+use crate::error::Error;
+use crate::token::{MaybeString, Punct, Token};
+use crate::tokenizer::{Result, Tokenizer};
+use serde::de;
+
+/// Splits `pointer` into its reference tokens, undoing `~1` and `~0`.
+pub fn parse(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = match pointer.strip_prefix('/') {
+        Some(rest) => rest,
+        None => return Err(Error::InvalidPointer(pointer.to_string())),
+    };
+    rest.split('/')
+        .map(|token| {
+            let mut out = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '~' => match chars.next() {
+                        Some('0') => out.push('~'),
+                        Some('1') => out.push('/'),
+                        _ => return Err(Error::InvalidPointer(pointer.to_string())),
+                    },
+                    ch => out.push(ch),
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+/// Escapes `token` for use as one reference token of a pointer.
+pub fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// The array index a reference token names. RFC 6901 allows no sign and no
+/// leading zeros; `-`, the element past the end, never exists in a lookup.
+pub fn array_index(token: &str) -> Option<usize> {
+    match token.as_bytes() {
+        [b'0'] => Some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => token.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Finds the value `pointer` refers to in `input`, and returns its source
+/// text and span. `None` if there is no such value. Only the input up to
+/// the end of the target is scanned, and only that much has to be valid.
+pub fn find<'a>(input: &'a str, pointer: &str) -> Result<Option<(&'a str, (usize, usize))>> {
+    let tokens = parse(pointer)?;
+    let mut tokenizer = Tokenizer::new(input);
+    for token in &tokens {
+        let found = match tokenizer.next()? {
+            Token::Punct(Punct::LeftCurly, _) => find_member(&mut tokenizer, token)?,
+            Token::Punct(Punct::LeftBracket, _) => match array_index(token) {
+                Some(index) => find_element(&mut tokenizer, index)?,
+                None => false,
+            },
+            Token::Eof => return Err(Error::EofWhileParsingValue(tokenizer.pos())),
+            _ => false,
+        };
+        if !found {
+            return Ok(None);
+        }
+    }
+    tokenizer.eat_whitespace();
+    let start = tokenizer.pos();
+    tokenizer.skip_value()?;
+    let end = tokenizer.pos();
+    Ok(Some((&input[start..end], (start, end))))
+}
+
+/// Deserializes the value `pointer` refers to in `input` as a `T`. Errors
+/// report positions in `input`.
+pub fn get<'a, T>(input: &'a str, pointer: &str) -> Result<Option<T>>
+where
+    T: de::Deserialize<'a>,
+{
+    match find(input, pointer)? {
+        Some((_, (start, _))) => {
+            let mut de = Deserializer::starting_at(input, start);
+            Ok(Some(T::deserialize(&mut de)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Moves past the `:` of the member named `name`, with the tokenizer just
+/// inside an object.
+fn find_member(tokenizer: &mut Tokenizer, name: &str) -> Result<bool> {
+    let mut first = true;
+    loop {
+        let key = match tokenizer.next()? {
+            Token::Punct(Punct::RightCurly, _) => return Ok(false),
+            Token::Punct(Punct::Comma, _) if !first => match tokenizer.next()? {
+                Token::String(key, _) => key,
+                _ => return Err(Error::JSONKeyMustBeString()),
+            },
+            Token::String(key, _) if first => key,
+            token => return Err(tokenizer.unexpected(&token, Punct::RightCurly)),
+        };
+        first = false;
+        tokenizer.expect(Punct::Colon)?;
+        let matches = match &key {
+            MaybeString::Escaped(key) => key == name,
+            MaybeString::NotEscaped(key) => *key == name,
+        };
+        if matches {
+            return Ok(true);
+        }
+        tokenizer.skip_value()?;
+    }
+}
+
+/// Moves to element `index`, with the tokenizer just inside an array.
+fn find_element(tokenizer: &mut Tokenizer, index: usize) -> Result<bool> {
+    if !tokenizer.element_start(true)? {
+        return Ok(false);
+    }
+    for _ in 0..index {
+        tokenizer.skip_value()?;
+        if !tokenizer.element_start(false)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[test]
+fn test_pointer() {
+    let s = r#"{"data": {"skip": [1, {"x": "]"}], "items": [{"id": 7}, {"id": 8, "a/b": 1, "m~n": [true]}]}, "": 0}"#;
+    assert_eq!(find(s, "/data/items/1/id").unwrap().unwrap().0, "8");
+    assert_eq!(get::<u32>(s, "/data/items/0/id").unwrap(), Some(7));
+    assert_eq!(get::<u32>(s, "/data/items/1/a~1b").unwrap(), Some(1));
+    assert_eq!(get::<bool>(s, "/data/items/1/m~0n/0").unwrap(), Some(true));
+    assert_eq!(get::<u32>(s, "/").unwrap(), Some(0));
+    assert_eq!(find(s, "").unwrap().unwrap().1, (0, s.len()));
+
+    let (raw, (start, end)) = find(s, "/data/skip").unwrap().unwrap();
+    assert_eq!(raw, r#"[1, {"x": "]"}]"#);
+    assert_eq!(&s[start..end], raw);
+
+    assert_eq!(find(s, "/data/items/2").unwrap(), None);
+    assert_eq!(find(s, "/data/items/-").unwrap(), None);
+    assert_eq!(find(s, "/data/items/01").unwrap(), None);
+    assert_eq!(find(s, "/data/nope").unwrap(), None);
+    assert_eq!(find(s, "/data/items/0/id/x").unwrap(), None);
+
+    assert_eq!(
+        find(s, "data"),
+        Err(Error::InvalidPointer("data".to_string()))
+    );
+    assert_eq!(
+        find(s, "/a~2"),
+        Err(Error::InvalidPointer("/a~2".to_string()))
+    );
+    // Input after the target is never looked at.
+    assert_eq!(get::<u32>(r#"{"a": 1, "b": ]"#, "/a").unwrap(), Some(1));
+    assert_eq!(escape("a/~b"), "a~1~0b");
+    assert_eq!(parse("/a~1~0b/").unwrap(), ["a/~b", ""]);
+}
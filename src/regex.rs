@@ -0,0 +1,531 @@
+//! A small regular expression engine for JSON Schema `pattern` and
+//! `patternProperties`.
+//!
+//! It covers the part of ECMA-262 syntax that schemas use in practice:
+//! literals, `.`, classes with ranges, `\d \w \s` and their negations,
+//! anchors, groups, alternation and greedy or lazy quantifiers. Lookaround,
+//! backreferences and `\b` are rejected when the pattern is parsed. Like
+//! JSON Schema, matching is a search: the pattern may match anywhere.
+//!
+//! Patterns are compiled to a program for a Pike VM, which runs every
+//! thread of the match in lock step over the text, so matching takes time
+//! proportional to the text times the program and never backtracks or
+//! recurses. Counted repetitions are written out in the program, which is
+//! capped at [`MAX_PROGRAM`] instructions; a larger pattern is rejected.
+//! Greedy and lazy quantifiers only differ in what they capture, so they
+//! match the same texts here.
+
+/// The most instructions a compiled pattern may have.
+const MAX_PROGRAM: usize = 10_000;
+
+pub(crate) struct Regex {
+    program: Vec<Inst>,
+}
+
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: usize,
+    },
+}
+
+impl Node {
+    /// The number of instructions the node compiles to, or `usize::MAX` if
+    /// that does not fit.
+    fn size(&self) -> usize {
+        let sum = |nodes: &[Node]| {
+            nodes
+                .iter()
+                .fold(0, |n: usize, node| n.saturating_add(node.size()))
+        };
+        match self {
+            Node::Group(alts) => alts
+                .iter()
+                .fold(0, |n, alt| n.saturating_add(sum(alt).saturating_add(2))),
+            Node::Repeat { node, min, max } => {
+                let copies = if *max == usize::MAX {
+                    min.saturating_add(1)
+                } else {
+                    *max
+                };
+                copies.saturating_mul(node.size().saturating_add(2))
+            }
+            _ => 1,
+        }
+    }
+}
+
+/// An instruction of the compiled program.
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    /// Continue at both targets.
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+#[derive(Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, ch: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= ch && ch <= hi,
+            ClassItem::Digit(negated) => ch.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => (ch.is_ascii_alphanumeric() || ch == '_') != negated,
+            ClassItem::Space(negated) => ch.is_whitespace() != negated,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, ch: char) -> bool {
+        self.items.iter().any(|item| item.matches(ch)) != self.negated
+    }
+}
+
+impl Regex {
+    /// Parses `pattern`. On failure, returns the index of the offending
+    /// character, or 0 if the program would exceed [`MAX_PROGRAM`].
+    pub(crate) fn new(pattern: &str) -> Result<Regex, usize> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alts = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.pos);
+        }
+        let root = Node::Group(alts);
+        if root.size() > MAX_PROGRAM {
+            return Err(0);
+        }
+        let mut program = Vec::new();
+        compile(&root, &mut program);
+        program.push(Inst::Match);
+        Ok(Regex { program })
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let mut threads = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut stack = Vec::new();
+        let mut chars = text.chars().peekable();
+        let mut at_start = true;
+        loop {
+            let at_end = chars.peek().is_none();
+            // A thread starts at every position, which makes this a search.
+            if self.add(&mut threads, 0, at_start, at_end, &mut stack) {
+                return true;
+            }
+            let ch = match chars.next() {
+                Some(ch) => ch,
+                None => return false,
+            };
+            let at_end = chars.peek().is_none();
+            for &pc in &threads.dense {
+                let step = match &self.program[pc] {
+                    Inst::Char(c) => *c == ch,
+                    Inst::Any => ch != '\n' && ch != '\r',
+                    Inst::Class(class) => class.matches(ch),
+                    _ => false,
+                };
+                if step && self.add(&mut next, pc + 1, false, at_end, &mut stack) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut threads, &mut next);
+            next.clear();
+            at_start = false;
+        }
+    }
+
+    /// Adds the thread at `pc` and every thread reachable from it without
+    /// reading a character. Returns whether one of them matched.
+    fn add(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        at_start: bool,
+        at_end: bool,
+        stack: &mut Vec<usize>,
+    ) -> bool {
+        stack.push(pc);
+        let mut matched = false;
+        while let Some(pc) = stack.pop() {
+            if !threads.insert(pc) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Jmp(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Start if at_start => stack.push(pc + 1),
+                Inst::End if at_end => stack.push(pc + 1),
+                Inst::Match => matched = true,
+                _ => {}
+            }
+        }
+        matched
+    }
+}
+
+/// A set of program counters that keeps insertion order and clears in time
+/// proportional to its size.
+struct Threads {
+    dense: Vec<usize>,
+    member: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            dense: Vec::with_capacity(len),
+            member: vec![false; len],
+        }
+    }
+
+    fn insert(&mut self, pc: usize) -> bool {
+        !std::mem::replace(&mut self.member[pc], true) && {
+            self.dense.push(pc);
+            true
+        }
+    }
+
+    fn clear(&mut self) {
+        for pc in self.dense.drain(..) {
+            self.member[pc] = false;
+        }
+    }
+}
+
+/// Appends the program for `node`.
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Char(ch) => program.push(Inst::Char(*ch)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(alts) => {
+            // Each alternative but the last is entered by a split whose
+            // other branch tries the next one, and jumps to the end.
+            let mut jumps = Vec::new();
+            for (i, alt) in alts.iter().enumerate() {
+                let split = program.len();
+                if i + 1 < alts.len() {
+                    program.push(Inst::Split(split + 1, 0));
+                }
+                for node in alt {
+                    compile(node, program);
+                }
+                if i + 1 < alts.len() {
+                    jumps.push(program.len());
+                    program.push(Inst::Jmp(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            if *max == usize::MAX {
+                let split = program.len();
+                program.push(Inst::Split(split + 1, 0));
+                compile(node, program);
+                program.push(Inst::Jmp(split));
+                let end = program.len();
+                program[split] = Inst::Split(split + 1, end);
+                return;
+            }
+            let mut splits = Vec::new();
+            for _ in *min..*max {
+                splits.push(program.len());
+                program.push(Inst::Split(program.len() + 1, 0));
+                compile(node, program);
+            }
+            let end = program.len();
+            for split in splits {
+                program[split] = Inst::Split(split + 1, end);
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, usize> {
+        let mut alts = vec![self.sequence()?];
+        while self.eat('|') {
+            alts.push(self.sequence()?);
+        }
+        Ok(alts)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, usize> {
+        let mut nodes = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, usize> {
+        let start = self.pos;
+        let ch = self.peek().ok_or(start)?;
+        self.pos += 1;
+        Ok(match ch {
+            '(' => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err(start + 1);
+                }
+                let alts = self.alternatives()?;
+                if !self.eat(')') {
+                    return Err(self.pos);
+                }
+                Node::Group(alts)
+            }
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => match self.escape()? {
+                Ok(ch) => Node::Char(ch),
+                Err(item) => Node::Class(Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+            },
+            '*' | '+' | '?' => return Err(start),
+            ch => Node::Char(ch),
+        })
+    }
+
+    /// The character or class an escape stands for, with the parser just
+    /// past the backslash.
+    fn escape(&mut self) -> Result<Result<char, ClassItem>, usize> {
+        let start = self.pos;
+        let ch = self.peek().ok_or(start)?;
+        self.pos += 1;
+        Ok(Ok(match ch {
+            'd' | 'D' => return Ok(Err(ClassItem::Digit(ch == 'D'))),
+            'w' | 'W' => return Ok(Err(ClassItem::Word(ch == 'W'))),
+            's' | 'S' => return Ok(Err(ClassItem::Space(ch == 'S'))),
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            '0' => '\0',
+            'x' => self.hex(2)?,
+            'u' => self.hex(4)?,
+            ch if ch.is_ascii_alphanumeric() => return Err(start),
+            ch => ch,
+        }))
+    }
+
+    fn hex(&mut self, len: usize) -> Result<char, usize> {
+        let start = self.pos;
+        let digits: String = self.chars.iter().skip(start).take(len).collect();
+        self.pos += len;
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(ch) if digits.len() == len => Ok(ch),
+            _ => Err(start),
+        }
+    }
+
+    fn class(&mut self) -> Result<Class, usize> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let lo = match self.class_char()? {
+                None => break,
+                Some(Ok(ch)) => ch,
+                Some(Err(item)) => {
+                    items.push(item);
+                    continue;
+                }
+            };
+            let hi = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                match self.class_char()? {
+                    Some(Ok(hi)) if lo <= hi => hi,
+                    _ => return Err(self.pos - 1),
+                }
+            } else {
+                lo
+            };
+            items.push(ClassItem::Range(lo, hi));
+        }
+        Ok(Class { items, negated })
+    }
+
+    /// The next member of a class, or `None` at its closing `]`.
+    fn class_char(&mut self) -> Result<Option<Result<char, ClassItem>>, usize> {
+        let ch = self.peek().ok_or(self.pos)?;
+        self.pos += 1;
+        Ok(match ch {
+            ']' => None,
+            '\\' if self.eat('b') => Some(Ok('\x08')),
+            '\\' => Some(self.escape()?),
+            ch => Some(Ok(ch)),
+        })
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, usize> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            Some('{') => match self.braces() {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        // Lazy or not, the same texts match.
+        self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}`, leaving the parser on its last
+    /// character. `None`, with the parser unmoved, if this is not a
+    /// quantifier, in which case the `{` is a literal.
+    fn braces(&mut self) -> Option<(usize, usize)> {
+        let start = self.pos;
+        self.pos += 1;
+        let bounds = self.bounds();
+        match bounds {
+            Some(_) => self.pos -= 1,
+            None => self.pos = start,
+        }
+        bounds
+    }
+
+    fn bounds(&mut self) -> Option<(usize, usize)> {
+        let min = self.number()?;
+        let max = if self.eat(',') {
+            match self.peek() {
+                Some('}') => usize::MAX,
+                _ => self.number()?,
+            }
+        } else {
+            min
+        };
+        if !self.eat('}') || min > max {
+            return None;
+        }
+        Some((min, max))
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok()
+    }
+}
+
+#[test]
+fn test_regex() {
+    let is_match = |pattern: &str, text: &str| Regex::new(pattern).unwrap().is_match(text);
+    assert!(is_match("^[a-z]+$", "abc"));
+    assert!(!is_match("^[a-z]+$", "abC"));
+    assert!(is_match("b", "abc"));
+    assert!(is_match(r"^\d{3}-\d{4}$", "555-1234"));
+    assert!(!is_match(r"^\d{3}-\d{4}$", "555-12345"));
+    assert!(is_match("^(cat|dog)s?$", "dogs"));
+    assert!(!is_match("^(cat|dog)s?$", "cow"));
+    assert!(is_match("^a{2,}?b$", "aaab"));
+    assert!(is_match("^(?:a*)*b$", "aab"));
+    assert!(is_match("^(a*){2}$", ""));
+    assert!(is_match(
+        r"^[^\s@]+@[\w.-]+\.[a-z]{2,}$",
+        "x.y@mail.example.org"
+    ));
+    assert!(is_match(r"^[\-+]?é\.$", "-é."));
+    assert!(is_match("^a{,2}$", "a{,2}"));
+    assert!(Regex::new("(?=a)").is_err());
+    assert!(Regex::new(r"\1").is_err());
+    assert!(Regex::new("a)").is_err());
+    assert!(Regex::new("[z-a]").is_err());
+    assert!(Regex::new("*a").is_err());
+
+    // Long texts neither overflow the stack nor take quadratic time, and
+    // nested quantifiers do not backtrack exponentially.
+    let long = "a".repeat(100_000);
+    assert!(is_match("^[a-z]+$", &long));
+    assert!(!is_match("^[a-z]+$", &(long.clone() + "!")));
+    assert!(!is_match("^(a+)+$", &(long.clone() + "!")));
+    assert!(!is_match("(a|aa)*b", &long));
+    assert!(is_match("^a{3,5}$", "aaaaa"));
+    assert!(!is_match("^a{3,5}$", "aaaaaa"));
+    assert!(is_match("^(ab|a)*?c", "ababac"));
+    // Counted repetitions are written out, up to a limit.
+    assert!(Regex::new("a{1000}").is_ok());
+    assert!(Regex::new("(a{100}){100}").is_err());
+    assert!(Regex::new("((((a{1000000}){1000000}){1000000}){1000000}){2}").is_err());
+    assert!(Regex::new("a{18446744073709551615,}").is_err());
+}
@@ -0,0 +1,256 @@
+//! Block scanning for the two hot loops of the tokenizer: finding the end of
+//! a run of plain string bytes, and skipping whitespace.
+//!
+//! With the `simd` feature, x86_64 scans 32 bytes at a time with AVX2 when
+//! the CPU has it and 16 with SSE2 otherwise, and other targets scan 8 bytes
+//! at a time in a `u64` (SWAR). Every path returns the same position as the
+//! plain byte loops in [`scalar`], which are used without the feature.
+
+/// Returns the position of the first `"`, `\` or control byte at or after
+/// `pos`, or `bytes.len()` if there is none.
+#[inline]
+pub fn find_string_special(bytes: &[u8], pos: usize) -> usize {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { x86::avx2::find_string_special(bytes, pos) };
+        }
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        unsafe { x86::sse2::find_string_special(bytes, pos) }
+    }
+    #[cfg(all(feature = "simd", not(target_arch = "x86_64")))]
+    {
+        swar::find_string_special(bytes, pos)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        scalar::find_string_special(bytes, pos)
+    }
+}
+
+/// Returns the position of the first non-whitespace byte at or after `pos`,
+/// or `bytes.len()` if there is none.
+#[inline]
+pub fn skip_whitespace(bytes: &[u8], pos: usize) -> usize {
+    // Compact documents rarely have whitespace at all; don't set up a block
+    // scan just to find that out.
+    match bytes.get(pos) {
+        Some(&b) if is_whitespace(b) => {}
+        _ => return pos,
+    }
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { x86::avx2::skip_whitespace(bytes, pos) };
+        }
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        unsafe { x86::sse2::skip_whitespace(bytes, pos) }
+    }
+    #[cfg(all(feature = "simd", not(target_arch = "x86_64")))]
+    {
+        swar::skip_whitespace(bytes, pos)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        scalar::skip_whitespace(bytes, pos)
+    }
+}
+
+#[inline]
+pub fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+#[inline]
+fn is_string_special(b: u8) -> bool {
+    b == b'"' || b == b'\\' || b < 0x20
+}
+
+pub mod scalar {
+    use super::{is_string_special, is_whitespace};
+
+    pub fn find_string_special(bytes: &[u8], mut pos: usize) -> usize {
+        while pos < bytes.len() && !is_string_special(bytes[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    pub fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+        while pos < bytes.len() && is_whitespace(bytes[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+}
+
+#[cfg_attr(not(feature = "simd"), allow(dead_code))]
+mod swar {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const HIGHS: u64 = 0x8080_8080_8080_8080;
+    const LOWS: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+
+    /// Sets the high bit of exactly the bytes of `x` that are zero. Unlike
+    /// the shorter `(x - ONES) & !x` trick, no borrow crosses bytes, so
+    /// there are no false positives to rule out.
+    #[inline]
+    fn zero_bytes(x: u64) -> u64 {
+        !(((x & LOWS) + LOWS) | x | LOWS)
+    }
+
+    #[inline]
+    fn load(bytes: &[u8], pos: usize) -> u64 {
+        let mut word = [0; 8];
+        word.copy_from_slice(&bytes[pos..pos + 8]);
+        u64::from_le_bytes(word)
+    }
+
+    pub fn find_string_special(bytes: &[u8], mut pos: usize) -> usize {
+        while pos + 8 <= bytes.len() {
+            let x = load(bytes, pos);
+            let found = zero_bytes(x ^ (ONES * b'"' as u64))
+                | zero_bytes(x ^ (ONES * b'\\' as u64))
+                | zero_bytes(x & (ONES * 0xE0));
+            if found != 0 {
+                return pos + (found.trailing_zeros() / 8) as usize;
+            }
+            pos += 8;
+        }
+        super::scalar::find_string_special(bytes, pos)
+    }
+
+    pub fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+        while pos + 8 <= bytes.len() {
+            let x = load(bytes, pos);
+            let ws = zero_bytes(x ^ (ONES * b' ' as u64))
+                | zero_bytes(x ^ (ONES * b'\t' as u64))
+                | zero_bytes(x ^ (ONES * b'\n' as u64))
+                | zero_bytes(x ^ (ONES * b'\r' as u64));
+            let other = !ws & HIGHS;
+            if other != 0 {
+                return pos + (other.trailing_zeros() / 8) as usize;
+            }
+            pos += 8;
+        }
+        super::scalar::skip_whitespace(bytes, pos)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod x86 {
+    /// Defines both scanners for one instruction set. The block tests are
+    /// the same at every width; the tail shorter than a block goes to SWAR.
+    macro_rules! scanners {
+        ($isa:ident, $feature:literal, $width:literal, $vec:ty, $load:ident, $set1:ident,
+         $cmpeq:ident, $max:ident, $or:ident, $movemask:ident) => {
+            pub mod $isa {
+                use std::arch::x86_64::*;
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn find_string_special(bytes: &[u8], mut pos: usize) -> usize {
+                    while pos + $width <= bytes.len() {
+                        // SAFETY: the block is in bounds, and the load is unaligned.
+                        let x = unsafe { $load(bytes.as_ptr().add(pos) as *const $vec) };
+                        let quote = $cmpeq(x, $set1(b'"' as i8));
+                        let backslash = $cmpeq(x, $set1(b'\\' as i8));
+                        // Unsigned `x <= 0x1F` is `max(x, 0x1F) == 0x1F`.
+                        let control = $cmpeq($max(x, $set1(0x1F)), $set1(0x1F));
+                        let mask = $movemask($or($or(quote, backslash), control)) as u32;
+                        if mask != 0 {
+                            return pos + mask.trailing_zeros() as usize;
+                        }
+                        pos += $width;
+                    }
+                    super::super::swar::find_string_special(bytes, pos)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+                    while pos + $width <= bytes.len() {
+                        // SAFETY: the block is in bounds, and the load is unaligned.
+                        let x = unsafe { $load(bytes.as_ptr().add(pos) as *const $vec) };
+                        let ws = $or(
+                            $or($cmpeq(x, $set1(b' ' as i8)), $cmpeq(x, $set1(b'\t' as i8))),
+                            $or($cmpeq(x, $set1(b'\n' as i8)), $cmpeq(x, $set1(b'\r' as i8))),
+                        );
+                        let mask = !($movemask(ws) as u32) & (u32::MAX >> (32 - $width));
+                        if mask != 0 {
+                            return pos + mask.trailing_zeros() as usize;
+                        }
+                        pos += $width;
+                    }
+                    super::super::swar::skip_whitespace(bytes, pos)
+                }
+            }
+        };
+    }
+
+    scanners!(
+        sse2,
+        "sse2",
+        16,
+        __m128i,
+        _mm_loadu_si128,
+        _mm_set1_epi8,
+        _mm_cmpeq_epi8,
+        _mm_max_epu8,
+        _mm_or_si128,
+        _mm_movemask_epi8
+    );
+    scanners!(
+        avx2,
+        "avx2",
+        32,
+        __m256i,
+        _mm256_loadu_si256,
+        _mm256_set1_epi8,
+        _mm256_cmpeq_epi8,
+        _mm256_max_epu8,
+        _mm256_or_si256,
+        _mm256_movemask_epi8
+    );
+}
+
+#[test]
+fn test_scan_paths_agree() {
+    let mut x: u32 = 0x2545_f491;
+    let mut next = move || {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        x
+    };
+    let alphabet = b" \t\r\n\"\\\x01\x1f\x20a\xc3\xa9{}:,";
+    for len in 0..80 {
+        for _ in 0..50 {
+            // Mostly whitespace or plain bytes, so matches land at every
+            // offset within a block.
+            let filler = if next() % 2 == 0 { b' ' } else { b'a' };
+            let bytes: Vec<u8> = (0..len)
+                .map(|_| match next() % 8 {
+                    0 => alphabet[next() as usize % alphabet.len()],
+                    _ => filler,
+                })
+                .collect();
+            for pos in 0..=len {
+                let string = scalar::find_string_special(&bytes, pos);
+                let ws = scalar::skip_whitespace(&bytes, pos);
+                assert_eq!(find_string_special(&bytes, pos), string);
+                assert_eq!(skip_whitespace(&bytes, pos), ws);
+                assert_eq!(swar::find_string_special(&bytes, pos), string);
+                assert_eq!(swar::skip_whitespace(&bytes, pos), ws);
+                #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+                unsafe {
+                    assert_eq!(x86::sse2::find_string_special(&bytes, pos), string);
+                    assert_eq!(x86::sse2::skip_whitespace(&bytes, pos), ws);
+                    if std::is_x86_feature_detected!("avx2") {
+                        assert_eq!(x86::avx2::find_string_special(&bytes, pos), string);
+                        assert_eq!(x86::avx2::skip_whitespace(&bytes, pos), ws);
+                    }
+                }
+            }
+        }
+    }
+}
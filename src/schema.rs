@@ -0,0 +1,807 @@
+//! JSON Schema (draft 2020-12) validation.
+//!
+//! A schema is compiled once into a [`Validator`]: every subschema becomes a
+//! node in a flat list, and a local `$ref` is a link to the node of its
+//! target, so recursive schemas cost nothing extra. Supported keywords are
+//! `type`, `enum`, `const`, the numeric, string and array bounds, `pattern`,
+//! `properties`, `patternProperties`, `additionalProperties`, `required`,
+//! `prefixItems`, `items`, `uniqueItems`, `allOf`, `anyOf`, `oneOf`, `not`
+//! and `$ref` to `#` fragments; annotations such as `$defs`, `title` and
+//! `format` are ignored.
+//!
+//! Each failure carries two JSON Pointers: the instance path of the value
+//! that failed, and the schema path of the keyword that rejected it, which
+//! runs through every `$ref` followed on the way.
+
+use crate::de::DuplicateKeys;
+use crate::error::Error;
+use crate::pointer;
+use crate::regex::Regex;
+use crate::tokenizer::Result;
+use crate::value::{Number, Value};
+use std::cmp::Ordering::{Greater, Less};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A compiled schema.
+pub struct Validator {
+    nodes: Vec<Node>,
+}
+
+/// One way an instance fails its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} at {:?} (schema {:?})",
+            self.message, self.instance_path, self.schema_path
+        )
+    }
+}
+
+enum Node {
+    Bool(bool),
+    Keywords(Vec<Keyword>),
+}
+
+enum Keyword {
+    Type(Vec<String>),
+    Enum(Vec<Value>),
+    Const(Value),
+    Minimum(Number),
+    Maximum(Number),
+    ExclusiveMinimum(Number),
+    ExclusiveMaximum(Number),
+    MultipleOf(f64),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex, String),
+    MinItems(usize),
+    MaxItems(usize),
+    UniqueItems,
+    Required(Vec<String>),
+    Properties(Vec<(String, usize)>),
+    PatternProperties(Vec<(String, Regex, usize)>),
+    AdditionalProperties {
+        node: usize,
+        properties: Vec<String>,
+        patterns: Vec<Regex>,
+    },
+    PrefixItems(Vec<usize>),
+    Items {
+        node: usize,
+        skip: usize,
+    },
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    Ref(usize),
+}
+
+impl Keyword {
+    fn name(&self) -> &'static str {
+        match self {
+            Keyword::Type(_) => "type",
+            Keyword::Enum(_) => "enum",
+            Keyword::Const(_) => "const",
+            Keyword::Minimum(_) => "minimum",
+            Keyword::Maximum(_) => "maximum",
+            Keyword::ExclusiveMinimum(_) => "exclusiveMinimum",
+            Keyword::ExclusiveMaximum(_) => "exclusiveMaximum",
+            Keyword::MultipleOf(_) => "multipleOf",
+            Keyword::MinLength(_) => "minLength",
+            Keyword::MaxLength(_) => "maxLength",
+            Keyword::Pattern(..) => "pattern",
+            Keyword::MinItems(_) => "minItems",
+            Keyword::MaxItems(_) => "maxItems",
+            Keyword::UniqueItems => "uniqueItems",
+            Keyword::Required(_) => "required",
+            Keyword::Properties(_) => "properties",
+            Keyword::PatternProperties(_) => "patternProperties",
+            Keyword::AdditionalProperties { .. } => "additionalProperties",
+            Keyword::PrefixItems(_) => "prefixItems",
+            Keyword::Items { .. } => "items",
+            Keyword::AllOf(_) => "allOf",
+            Keyword::AnyOf(_) => "anyOf",
+            Keyword::OneOf(_) => "oneOf",
+            Keyword::Not(_) => "not",
+            Keyword::Ref(_) => "$ref",
+        }
+    }
+}
+
+const TYPES: [&str; 7] = [
+    "null", "boolean", "integer", "number", "string", "array", "object",
+];
+
+impl Validator {
+    /// Compiles `schema`. Fails with [`Error::InvalidSchema`], naming the
+    /// schema path of the offending keyword, if a keyword has the wrong
+    /// shape, a pattern is not supported, a `$ref` cannot be resolved, or
+    /// `$ref`s and applicators such as `allOf` loop back to a schema without
+    /// reading into the instance.
+    pub fn new(schema: &Value) -> Result<Validator> {
+        let mut compiler = Compiler {
+            root: schema,
+            nodes: Vec::new(),
+            places: Vec::new(),
+            refs: HashMap::new(),
+        };
+        compiler.reference("", "")?;
+        compiler.reject_cycles()?;
+        Ok(Validator {
+            nodes: compiler.nodes,
+        })
+    }
+
+    /// Every way `instance` fails the schema; empty if it is valid.
+    pub fn validate(&self, instance: &Value) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.check(
+            0,
+            instance,
+            &mut String::new(),
+            &mut String::new(),
+            &mut errors,
+        );
+        errors
+    }
+
+    pub fn is_valid(&self, instance: &Value) -> bool {
+        self.validate(instance).is_empty()
+    }
+
+    fn check(
+        &self,
+        node: usize,
+        value: &Value,
+        instance_path: &mut String,
+        schema_path: &mut String,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let keywords = match &self.nodes[node] {
+            Node::Bool(true) => return,
+            Node::Bool(false) => {
+                let message = "no value is allowed here".to_string();
+                return fail(errors, instance_path, schema_path, message);
+            }
+            Node::Keywords(keywords) => keywords,
+        };
+        for keyword in keywords {
+            nested(schema_path, keyword.name(), |schema_path| {
+                self.keyword(keyword, value, instance_path, schema_path, errors)
+            });
+        }
+    }
+
+    /// Whether `value` passes node `node`; for combinators, which report
+    /// failures of their own rather than those of their subschemas.
+    fn passes(&self, node: usize, value: &Value, instance_path: &mut String) -> bool {
+        let mut errors = Vec::new();
+        self.check(node, value, instance_path, &mut String::new(), &mut errors);
+        errors.is_empty()
+    }
+
+    fn keyword(
+        &self,
+        keyword: &Keyword,
+        value: &Value,
+        instance_path: &mut String,
+        schema_path: &mut String,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let message = match (keyword, value) {
+            (Keyword::Type(types), value) if !types.iter().any(|t| has_type(value, t)) => {
+                format!("expected {}, found {}", types.join(" or "), value.kind())
+            }
+            (Keyword::Enum(values), value) if !values.iter().any(|v| v.same_as(value)) => {
+                "value is not one of the allowed values".to_string()
+            }
+            (Keyword::Const(constant), value) if !constant.same_as(value) => {
+                "value is not the required constant".to_string()
+            }
+            (Keyword::Minimum(min), Value::Number(n)) if n.compare(*min) == Some(Less) => {
+                format!("{} is less than the minimum {}", n, min)
+            }
+            (Keyword::Maximum(max), Value::Number(n)) if n.compare(*max) == Some(Greater) => {
+                format!("{} is greater than the maximum {}", n, max)
+            }
+            (Keyword::ExclusiveMinimum(min), Value::Number(n))
+                if n.compare(*min) != Some(Greater) =>
+            {
+                format!("{} is not greater than {}", n, min)
+            }
+            (Keyword::ExclusiveMaximum(max), Value::Number(n)) if n.compare(*max) != Some(Less) => {
+                format!("{} is not less than {}", n, max)
+            }
+            (Keyword::MultipleOf(m), Value::Number(n)) if !is_multiple(*n, *m) => {
+                format!("{} is not a multiple of {}", n.as_f64(), m)
+            }
+            (Keyword::MinLength(min), Value::String(s)) if s.chars().count() < *min => {
+                format!("string is shorter than {} characters", min)
+            }
+            (Keyword::MaxLength(max), Value::String(s)) if s.chars().count() > *max => {
+                format!("string is longer than {} characters", max)
+            }
+            (Keyword::Pattern(regex, source), Value::String(s)) if !regex.is_match(s) => {
+                format!("string does not match {:?}", source)
+            }
+            (Keyword::MinItems(min), Value::Array(items)) if items.len() < *min => {
+                format!("array has fewer than {} items", min)
+            }
+            (Keyword::MaxItems(max), Value::Array(items)) if items.len() > *max => {
+                format!("array has more than {} items", max)
+            }
+            (Keyword::UniqueItems, Value::Array(items)) => {
+                let duplicate = (0..items.len()).find_map(|j| {
+                    let i = (0..j).find(|&i| items[i].same_as(&items[j]))?;
+                    Some((i, j))
+                });
+                match duplicate {
+                    Some((i, j)) => format!("items {} and {} are equal", i, j),
+                    None => return,
+                }
+            }
+            (Keyword::Required(names), Value::Object(map)) => {
+                for name in names.iter().filter(|name| !map.contains_key(*name)) {
+                    let message = format!("missing required property {:?}", name);
+                    fail(errors, instance_path, schema_path, message);
+                }
+                return;
+            }
+            (Keyword::Properties(properties), Value::Object(map)) => {
+                for (name, node) in properties {
+                    if let Some(member) = map.get(name) {
+                        let name = pointer::escape(name);
+                        nested(instance_path, &name, |instance_path| {
+                            nested(schema_path, &name, |schema_path| {
+                                self.check(*node, member, instance_path, schema_path, errors)
+                            })
+                        });
+                    }
+                }
+                return;
+            }
+            (Keyword::PatternProperties(patterns), Value::Object(map)) => {
+                for (name, member) in map {
+                    for (source, regex, node) in patterns {
+                        if regex.is_match(name) {
+                            nested(instance_path, &pointer::escape(name), |instance_path| {
+                                nested(schema_path, &pointer::escape(source), |schema_path| {
+                                    self.check(*node, member, instance_path, schema_path, errors)
+                                })
+                            });
+                        }
+                    }
+                }
+                return;
+            }
+            (
+                Keyword::AdditionalProperties {
+                    node,
+                    properties,
+                    patterns,
+                },
+                Value::Object(map),
+            ) => {
+                for (name, member) in map {
+                    if properties.contains(name) || patterns.iter().any(|r| r.is_match(name)) {
+                        continue;
+                    }
+                    nested(instance_path, &pointer::escape(name), |instance_path| {
+                        self.check(*node, member, instance_path, schema_path, errors)
+                    });
+                }
+                return;
+            }
+            (Keyword::PrefixItems(nodes), Value::Array(items)) => {
+                for (i, (node, item)) in nodes.iter().zip(items).enumerate() {
+                    let i = i.to_string();
+                    nested(instance_path, &i, |instance_path| {
+                        nested(schema_path, &i, |schema_path| {
+                            self.check(*node, item, instance_path, schema_path, errors)
+                        })
+                    });
+                }
+                return;
+            }
+            (Keyword::Items { node, skip }, Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate().skip(*skip) {
+                    nested(instance_path, &i.to_string(), |instance_path| {
+                        self.check(*node, item, instance_path, schema_path, errors)
+                    });
+                }
+                return;
+            }
+            (Keyword::AllOf(nodes), value) => {
+                for (i, node) in nodes.iter().enumerate() {
+                    nested(schema_path, &i.to_string(), |schema_path| {
+                        self.check(*node, value, instance_path, schema_path, errors)
+                    });
+                }
+                return;
+            }
+            (Keyword::AnyOf(nodes), value) => {
+                if nodes
+                    .iter()
+                    .any(|node| self.passes(*node, value, instance_path))
+                {
+                    return;
+                }
+                "value matches none of the schemas".to_string()
+            }
+            (Keyword::OneOf(nodes), value) => {
+                let passed = nodes
+                    .iter()
+                    .filter(|node| self.passes(**node, value, instance_path))
+                    .count();
+                match passed {
+                    1 => return,
+                    0 => "value matches none of the schemas".to_string(),
+                    n => format!("value matches {} of the schemas, not exactly one", n),
+                }
+            }
+            (Keyword::Not(node), value) if self.passes(*node, value, instance_path) => {
+                "value matches a schema it must not".to_string()
+            }
+            (Keyword::Ref(node), value) => {
+                return self.check(*node, value, instance_path, schema_path, errors)
+            }
+            _ => return,
+        };
+        fail(errors, instance_path, schema_path, message);
+    }
+}
+
+/// Compiles `schema` and validates `instance` against it, both as text.
+pub fn validate(schema: &str, instance: &str) -> Result<Vec<ValidationError>> {
+    validate_with(schema, instance, DuplicateKeys::Allow)
+}
+
+/// Like [`validate`], reading objects with a repeated key in either text by
+/// `policy`.
+pub fn validate_with(
+    schema: &str,
+    instance: &str,
+    policy: DuplicateKeys,
+) -> Result<Vec<ValidationError>> {
+    let schema: Value = crate::de::from_str_with(schema, policy)?;
+    let instance: Value = crate::de::from_str_with(instance, policy)?;
+    Ok(Validator::new(&schema)?.validate(&instance))
+}
+
+fn fail(
+    errors: &mut Vec<ValidationError>,
+    instance_path: &str,
+    schema_path: &str,
+    message: String,
+) {
+    errors.push(ValidationError {
+        instance_path: instance_path.to_string(),
+        schema_path: schema_path.to_string(),
+        message,
+    });
+}
+
+/// Runs `f` with `/segment` appended to `path`.
+fn nested<T>(path: &mut String, segment: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let len = path.len();
+    path.push('/');
+    path.push_str(segment);
+    let result = f(path);
+    path.truncate(len);
+    result
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match (name, value) {
+        ("number", Value::Number(_)) => true,
+        // A float with no fractional part is an integer to JSON Schema.
+        ("integer", Value::Number(Number::F64(n))) => n.fract() == 0.0,
+        (name, value) => value.kind() == name,
+    }
+}
+
+fn is_multiple(n: Number, m: f64) -> bool {
+    // Integers, and floats with no fractional part below 2^63, divide
+    // exactly.
+    let whole = match n {
+        Number::U64(n) => Some(n as i128),
+        Number::I64(n) => Some(n as i128),
+        Number::F64(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => Some(f as i128),
+        Number::F64(_) => None,
+    };
+    if let Some(n) = whole {
+        if m.fract() == 0.0 && m < i64::MAX as f64 {
+            return n % (m as i128) == 0;
+        }
+    }
+    // Allow for a few ULPs of rounding in the quotient, so that 0.3 is a
+    // multiple of 0.1.
+    let quotient = n.as_f64() / m;
+    (quotient - quotient.round()).abs() <= 4.0 * f64::EPSILON * quotient.abs().max(1.0)
+}
+
+struct Compiler<'s> {
+    root: &'s Value,
+    nodes: Vec<Node>,
+    /// The schema path of each node.
+    places: Vec<String>,
+    /// The node of each `$ref` target compiled so far, by JSON Pointer.
+    refs: HashMap<String, usize>,
+}
+
+impl<'s> Compiler<'s> {
+    fn compile(&mut self, schema: &Value, path: &str) -> Result<usize> {
+        let node = self.node(schema, path)?;
+        self.nodes.push(node);
+        self.places.push(path.to_string());
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// The node for the schema at `target`, a JSON Pointer into the root.
+    /// The node is registered before it is compiled, so that references
+    /// back to it from inside resolve.
+    fn reference(&mut self, target: &str, path: &str) -> Result<usize> {
+        if let Some(&node) = self.refs.get(target) {
+            return Ok(node);
+        }
+        let root = self.root;
+        let schema = root.pointer(target).ok_or_else(|| invalid(path))?;
+        let node = self.nodes.len();
+        self.nodes.push(Node::Bool(true));
+        self.places.push(target.to_string());
+        self.refs.insert(target.to_string(), node);
+        self.nodes[node] = self.node(schema, target)?;
+        Ok(node)
+    }
+
+    /// Fails on a chain of `$ref`s and in-place applicators that leads back
+    /// to a node it passed through: checking it would never reach into the
+    /// instance, so it would never end. Names the keyword that closes the
+    /// loop.
+    fn reject_cycles(&self) -> Result<()> {
+        // 0: not seen, 1: on the chain being followed, 2: done.
+        let mut state = vec![0u8; self.nodes.len()];
+        for start in 0..self.nodes.len() {
+            if state[start] != 0 {
+                continue;
+            }
+            state[start] = 1;
+            let mut chain = vec![(start, self.in_place(start))];
+            while let Some((node, next)) = chain.last_mut() {
+                let node = *node;
+                match next.pop() {
+                    Some((to, keyword)) => match state[to] {
+                        0 => {
+                            state[to] = 1;
+                            chain.push((to, self.in_place(to)));
+                        }
+                        1 => return Err(invalid(&format!("{}/{}", self.places[node], keyword))),
+                        _ => {}
+                    },
+                    None => {
+                        state[node] = 2;
+                        chain.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The nodes that `node` applies to the instance it is given, each with
+    /// its schema path from `node`.
+    fn in_place(&self, node: usize) -> Vec<(usize, String)> {
+        let mut next = Vec::new();
+        if let Node::Keywords(keywords) = &self.nodes[node] {
+            for keyword in keywords {
+                match keyword {
+                    Keyword::AllOf(nodes) | Keyword::AnyOf(nodes) | Keyword::OneOf(nodes) => next
+                        .extend(
+                            nodes
+                                .iter()
+                                .enumerate()
+                                .map(|(i, &to)| (to, format!("{}/{}", keyword.name(), i))),
+                        ),
+                    Keyword::Not(to) | Keyword::Ref(to) => {
+                        next.push((*to, keyword.name().to_string()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        next
+    }
+
+    fn node(&mut self, schema: &Value, path: &str) -> Result<Node> {
+        let map = match schema {
+            Value::Bool(b) => return Ok(Node::Bool(*b)),
+            Value::Object(map) => map,
+            _ => return Err(invalid(path)),
+        };
+        let mut keywords = Vec::new();
+        for (name, value) in map {
+            let path = &format!("{}/{}", path, pointer::escape(name));
+            let keyword = match name.as_str() {
+                "type" => {
+                    let types = match value {
+                        Value::String(name) => vec![name.clone()],
+                        value => strings(value, path)?,
+                    };
+                    if !types.iter().all(|t| TYPES.contains(&t.as_str())) {
+                        return Err(invalid(path));
+                    }
+                    Keyword::Type(types)
+                }
+                "enum" => Keyword::Enum(value.as_array().ok_or_else(|| invalid(path))?.clone()),
+                "const" => Keyword::Const(value.clone()),
+                "minimum" => Keyword::Minimum(limit(value, path)?),
+                "maximum" => Keyword::Maximum(limit(value, path)?),
+                "exclusiveMinimum" => Keyword::ExclusiveMinimum(limit(value, path)?),
+                "exclusiveMaximum" => Keyword::ExclusiveMaximum(limit(value, path)?),
+                "multipleOf" => match number(value, path)? {
+                    m if m > 0.0 => Keyword::MultipleOf(m),
+                    _ => return Err(invalid(path)),
+                },
+                "minLength" => Keyword::MinLength(count(value, path)?),
+                "maxLength" => Keyword::MaxLength(count(value, path)?),
+                "pattern" => {
+                    let source = value.as_str().ok_or_else(|| invalid(path))?;
+                    Keyword::Pattern(regex(source, path)?, source.to_string())
+                }
+                "minItems" => Keyword::MinItems(count(value, path)?),
+                "maxItems" => Keyword::MaxItems(count(value, path)?),
+                "uniqueItems" => match value {
+                    Value::Bool(true) => Keyword::UniqueItems,
+                    Value::Bool(false) => continue,
+                    _ => return Err(invalid(path)),
+                },
+                "required" => Keyword::Required(strings(value, path)?),
+                "properties" => {
+                    let mut properties = Vec::new();
+                    for (name, schema) in value.as_object().ok_or_else(|| invalid(path))? {
+                        let at = format!("{}/{}", path, pointer::escape(name));
+                        properties.push((name.clone(), self.compile(schema, &at)?));
+                    }
+                    Keyword::Properties(properties)
+                }
+                "patternProperties" => {
+                    let mut patterns = Vec::new();
+                    for (source, schema) in value.as_object().ok_or_else(|| invalid(path))? {
+                        let at = format!("{}/{}", path, pointer::escape(source));
+                        let regex = regex(source, &at)?;
+                        patterns.push((source.clone(), regex, self.compile(schema, &at)?));
+                    }
+                    Keyword::PatternProperties(patterns)
+                }
+                "additionalProperties" => {
+                    let properties = map
+                        .get("properties")
+                        .and_then(Value::as_object)
+                        .map_or_else(Vec::new, |properties| properties.keys().cloned().collect());
+                    let mut patterns = Vec::new();
+                    if let Some(sources) = map.get("patternProperties").and_then(Value::as_object) {
+                        for source in sources.keys() {
+                            patterns.push(regex(source, path)?);
+                        }
+                    }
+                    Keyword::AdditionalProperties {
+                        node: self.compile(value, path)?,
+                        properties,
+                        patterns,
+                    }
+                }
+                "prefixItems" => Keyword::PrefixItems(self.compile_all(value, path)?),
+                "items" => Keyword::Items {
+                    node: self.compile(value, path)?,
+                    skip: map
+                        .get("prefixItems")
+                        .and_then(Value::as_array)
+                        .map_or(0, Vec::len),
+                },
+                "allOf" => Keyword::AllOf(self.compile_all(value, path)?),
+                "anyOf" => Keyword::AnyOf(self.compile_all(value, path)?),
+                "oneOf" => Keyword::OneOf(self.compile_all(value, path)?),
+                "not" => Keyword::Not(self.compile(value, path)?),
+                "$ref" => {
+                    let target = value
+                        .as_str()
+                        .and_then(|r| r.strip_prefix('#'))
+                        .ok_or_else(|| invalid(path))?;
+                    Keyword::Ref(self.reference(target, path)?)
+                }
+                _ => continue,
+            };
+            keywords.push(keyword);
+        }
+        Ok(Node::Keywords(keywords))
+    }
+
+    /// Compiles a non-empty array of schemas.
+    fn compile_all(&mut self, value: &Value, path: &str) -> Result<Vec<usize>> {
+        match value {
+            Value::Array(schemas) if !schemas.is_empty() => schemas
+                .iter()
+                .enumerate()
+                .map(|(i, schema)| self.compile(schema, &format!("{}/{}", path, i)))
+                .collect(),
+            _ => Err(invalid(path)),
+        }
+    }
+}
+
+fn invalid(path: &str) -> Error {
+    Error::InvalidSchema(path.to_string())
+}
+
+fn number(value: &Value, path: &str) -> Result<f64> {
+    value.as_f64().ok_or_else(|| invalid(path))
+}
+
+/// A bound, kept as written so integers compare exactly.
+fn limit(value: &Value, path: &str) -> Result<Number> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        _ => Err(invalid(path)),
+    }
+}
+
+fn count(value: &Value, path: &str) -> Result<usize> {
+    match value {
+        Value::Number(Number::U64(n)) => usize::try_from(*n).map_err(|_| invalid(path)),
+        Value::Number(Number::F64(n)) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(invalid(path)),
+    }
+}
+
+fn strings(value: &Value, path: &str) -> Result<Vec<String>> {
+    let items = value.as_array().ok_or_else(|| invalid(path))?;
+    items
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(path))
+        })
+        .collect()
+}
+
+fn regex(source: &str, path: &str) -> Result<Regex> {
+    Regex::new(source).map_err(|_| invalid(path))
+}
+
+#[test]
+fn test_schema() {
+    let schema = r##"{
+        "$defs": {
+            "node": {
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "minLength": 1, "pattern": "^[a-z]+$"},
+                    "children": {"type": "array", "items": {"$ref": "#/$defs/node"}}
+                },
+                "required": ["name"],
+                "additionalProperties": false
+            }
+        },
+        "type": "object",
+        "properties": {
+            "root": {"$ref": "#/$defs/node"},
+            "size": {"type": "integer", "minimum": 0, "exclusiveMaximum": 10, "multipleOf": 2},
+            "tag": {"enum": ["a", "b", 1]},
+            "pair": {"prefixItems": [{"type": "string"}, {"type": "number"}], "items": false},
+            "kind": {"oneOf": [{"const": "x"}, {"type": "string", "maxLength": 1}]},
+            "id": {"anyOf": [{"type": "integer"}, {"type": "string"}], "not": {"const": 0}}
+        },
+        "patternProperties": {"^x-": {"type": "boolean"}}
+    }"##;
+    let valid = r#"{
+        "root": {"name": "a", "children": [{"name": "b", "children": []}]},
+        "size": 4.0, "tag": 1.0, "pair": ["p", 2.5], "kind": "y", "id": "z", "x-flag": true
+    }"#;
+    assert_eq!(validate(schema, valid).unwrap(), []);
+
+    let invalid = r#"{
+        "root": {"children": [{"name": "B", "extra": 1}]},
+        "size": 11, "tag": "c", "pair": ["p", "q", 3], "kind": "x", "id": 0, "x-flag": 1
+    }"#;
+    let errors = validate(schema, invalid).unwrap();
+    let found: Vec<(&str, &str)> = errors
+        .iter()
+        .map(|e| (e.instance_path.as_str(), e.schema_path.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("/x-flag", "/patternProperties/^x-/type"),
+            ("/id", "/properties/id/not"),
+            ("/kind", "/properties/kind/oneOf"),
+            ("/pair/2", "/properties/pair/items"),
+            ("/pair/1", "/properties/pair/prefixItems/1/type"),
+            (
+                "/root/children/0/extra",
+                "/properties/root/$ref/properties/children/items/$ref/additionalProperties"
+            ),
+            (
+                "/root/children/0/name",
+                "/properties/root/$ref/properties/children/items/$ref/properties/name/pattern"
+            ),
+            ("/root", "/properties/root/$ref/required"),
+            ("/size", "/properties/size/exclusiveMaximum"),
+            ("/size", "/properties/size/multipleOf"),
+            ("/tag", "/properties/tag/enum"),
+        ]
+    );
+    assert_eq!(errors[7].message, "missing required property \"name\"");
+    assert_eq!(errors[4].message, "expected number, found string");
+
+    let check = |schema: &str, instance: &str| validate(schema, instance).unwrap().is_empty();
+    assert!(check(r#"{"multipleOf": 0.1}"#, "0.3"));
+    assert!(!check(r#"{"multipleOf": 2}"#, "3000000001.0"));
+    assert!(check(r#"{"multipleOf": 2}"#, "3000000002.0"));
+    assert!(!check(r#"{"multipleOf": 1}"#, "10000000000.5"));
+    // Bounds past 2^53 are compared exactly, not as rounded floats.
+    let big = r#"{"maximum": 9007199254740992}"#;
+    assert!(check(big, "9007199254740992"));
+    assert!(!check(big, "9007199254740993"));
+    assert!(!check(
+        r#"{"exclusiveMinimum": 9007199254740993}"#,
+        "9007199254740993"
+    ));
+    assert!(check(
+        r#"{"exclusiveMinimum": 9007199254740992.0}"#,
+        "9007199254740993"
+    ));
+    assert!(!check(r#"{"minimum": -1}"#, "-1.5"));
+    assert!(check(r#"{"uniqueItems": true}"#, "[1, 2]"));
+    assert!(!check(r#"{"uniqueItems": true}"#, "[1, 1.0]"));
+    assert!(!check(r#"{"minItems": 2}"#, "[1]"));
+    assert!(check(r#"{"minItems": 2}"#, r#""not an array""#));
+    assert!(!check("false", "null"));
+    assert!(check(
+        r##"{"type": ["null", "array"], "items": {"$ref": "#"}}"##,
+        "[[null], []]"
+    ));
+
+    let error = |schema: &str| match validate(schema, "null") {
+        Err(Error::InvalidSchema(path)) => path,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(error(r#"{"type": "int"}"#), "/type");
+    assert_eq!(
+        error(r#"{"properties": {"a": {"pattern": "(?=x)"}}}"#),
+        "/properties/a/pattern"
+    );
+    assert_eq!(error(r##"{"$ref": "#/$defs/missing"}"##), "/$ref");
+    assert_eq!(error(r#"{"allOf": []}"#), "/allOf");
+    assert_eq!(error(r##"{"$ref": "#"}"##), "/$ref");
+    assert_eq!(
+        error(
+            r##"{"$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##
+        ),
+        "/$defs/b/$ref"
+    );
+    assert_eq!(
+        error(r##"{"anyOf": [{"type": "null"}, {"not": {"$ref": "#"}}]}"##),
+        "/anyOf/1/not/$ref"
+    );
+
+    let twice = r#"{"type": "string", "type": "number"}"#;
+    assert!(validate(twice, "1").unwrap().is_empty());
+    assert!(validate_with(twice, "1", DuplicateKeys::Error).is_err());
+    assert_eq!(
+        validate_with(twice, "1", DuplicateKeys::FirstWins)
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -1,22 +1,212 @@
 use crate::error::{Error, Result};
+use serde::ser::Impossible;
 use serde::{ser, Serialize};
+use std::fmt::Write;
+
+/// How NaN and the infinities are written, since JSON has no token for them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFiniteFloat {
+    /// Fail with `Error::NonFiniteFloat`.
+    #[default]
+    Error,
+    /// Write `null`.
+    Null,
+    /// Write `"NaN"`, `"Infinity"` or `"-Infinity"`.
+    String,
+}
 
+#[derive(Default)]
 pub struct Serializer {
     output: String,
+    non_finite: NonFiniteFloat,
+    canonical: bool,
+    /// In canonical mode, the key and output offset of each member of the
+    /// objects being written, innermost last, to sort them when they end.
+    objects: Vec<Vec<(String, usize)>>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    pub fn non_finite_float(mut self, policy: NonFiniteFloat) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    /// Writes the RFC 8785 (JCS) canonical form: object members sorted by
+    /// the UTF-16 code units of their keys, numbers as ECMAScript prints
+    /// them, and no whitespace. Integers beyond 2^53 are written as the
+    /// nearest double, as JCS requires.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    pub fn into_string(self) -> String {
+        self.output
+    }
+
+    /// Writes `values` as an array in one loop, with none of the
+    /// per-element calls serializing a `&[f64]` goes through.
+    pub fn write_f64_array(&mut self, values: &[f64]) -> Result<()> {
+        // Most readings print in under 24 bytes with their separator.
+        self.output.reserve(values.len().saturating_mul(24) + 2);
+        self.output.push('[');
+        for (i, &v) in values.iter().enumerate() {
+            if i > 0 {
+                self.output.push(',');
+            }
+            self.write_f64(v)?;
+        }
+        self.output.push(']');
+        Ok(())
+    }
+
+    /// Writes `values` as an array in one loop, like
+    /// [`write_f64_array`](Serializer::write_f64_array).
+    pub fn write_i64_array(&mut self, values: &[i64]) -> Result<()> {
+        self.output.reserve(values.len().saturating_mul(8) + 2);
+        self.output.push('[');
+        for (i, &v) in values.iter().enumerate() {
+            if i > 0 {
+                self.output.push(',');
+            }
+            self.write_i64(v);
+        }
+        self.output.push(']');
+        Ok(())
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        if self.canonical && v.unsigned_abs() > MAX_SAFE_INTEGER {
+            write_es_number(&mut self.output, v as f64);
+            return;
+        }
+        if v < 0 {
+            self.output += "-";
+        }
+        self.output += format_u64(&mut [0; 20], v.unsigned_abs());
+    }
+
+    fn write_f64(&mut self, v: f64) -> Result<()> {
+        if !v.is_finite() {
+            return self.serialize_non_finite(v);
+        }
+        if self.canonical {
+            write_es_number(&mut self.output, v);
+            return Ok(());
+        }
+        write!(self.output, "{:?}", v).map_err(ser::Error::custom)
+    }
+
+    /// Starts an object member: writes the separator and returns where the
+    /// member begins.
+    fn begin_member(&mut self) -> usize {
+        if !self.output.ends_with('{') {
+            self.output.push(',');
+        }
+        self.output.len()
+    }
+
+    /// Records the member whose key was just written from `start`.
+    fn key_written(&mut self, start: usize) -> Result<()> {
+        if self.canonical {
+            let key: String = crate::de::from_str(&self.output[start..])?;
+            if let Some(members) = self.objects.last_mut() {
+                members.push((key, start));
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_object(&mut self) {
+        self.output.push('{');
+        if self.canonical {
+            self.objects.push(Vec::new());
+        }
+    }
+
+    /// In canonical mode, rewrites the members of the object being closed
+    /// in key order.
+    fn end_object(&mut self) {
+        let members = match self.objects.pop() {
+            Some(members) if self.canonical => members,
+            _ => return,
+        };
+        let first = match members.first() {
+            Some(&(_, start)) => start,
+            None => return,
+        };
+        // Each member runs up to the comma before the next one.
+        let ends = members
+            .iter()
+            .skip(1)
+            .map(|&(_, start)| start - 1)
+            .chain(std::iter::once(self.output.len()));
+        let mut sorted: Vec<(Vec<u16>, String)> = members
+            .iter()
+            .zip(ends)
+            .map(|((key, start), end)| {
+                (
+                    key.encode_utf16().collect(),
+                    self.output[*start..end].to_string(),
+                )
+            })
+            .collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        self.output.truncate(first);
+        for (i, (_, member)) in sorted.iter().enumerate() {
+            if i > 0 {
+                self.output.push(',');
+            }
+            self.output += member;
+        }
+    }
+
+    fn serialize_non_finite(&mut self, v: f64) -> Result<()> {
+        let name = if v.is_nan() {
+            "NaN"
+        } else if v.is_sign_positive() {
+            "Infinity"
+        } else {
+            "-Infinity"
+        };
+        match self.non_finite {
+            NonFiniteFloat::Error => return Err(Error::NonFiniteFloat(name)),
+            NonFiniteFloat::Null => self.output += "null",
+            NonFiniteFloat::String => {
+                self.output += "\"";
+                self.output += name;
+                self.output += "\"";
+            }
+        }
+        Ok(())
+    }
 }
 
 pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: Serialize,
 {
-    let mut serializer = Serializer {
-        output: String::new(),
-    };
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_string())
+}
+
+/// Serializes `value` in the RFC 8785 canonical form, so equal values always
+/// produce identical bytes, ready to hash or sign.
+pub fn to_canonical_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::new().canonical(true);
     value.serialize(&mut serializer)?;
-    Ok(serializer.output)
+    Ok(serializer.into_string())
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl ser::Serializer for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
@@ -53,7 +243,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_i64(self, v: i64) -> std::result::Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
+        self.write_i64(v);
         Ok(())
     }
 
@@ -70,16 +260,29 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        self.serialize_i64(v as i64)
+        if self.canonical && v > MAX_SAFE_INTEGER {
+            write_es_number(&mut self.output, v as f64);
+            return Ok(());
+        }
+        self.output += format_u64(&mut [0; 20], v);
+        Ok(())
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
-        self.serialize_f64(v as f64)
+        if !v.is_finite() {
+            return self.serialize_non_finite(v as f64);
+        }
+        if self.canonical {
+            write_es_number(&mut self.output, v as f64);
+            return Ok(());
+        }
+        // `Debug` prints the shortest digits that round-trip at this width
+        // and always keeps a `.` or exponent, so `1.0` stays a float.
+        write!(self.output, "{:?}", v).map_err(ser::Error::custom)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
-        self.output += &v.to_string();
-        Ok(())
+        self.write_f64(v)
     }
 
     fn serialize_char(self, v: char) -> std::result::Result<Self::Ok, Self::Error> {
@@ -87,9 +290,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_str(self, v: &str) -> std::result::Result<Self::Ok, Self::Error> {
-        self.output += "\"";
-        self.output += v;
-        self.output += "\"";
+        self.output.push('"');
+        escape_str(&mut self.output, v);
+        self.output.push('"');
         Ok(())
     }
 
@@ -106,9 +309,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self.serialize_unit()
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> std::result::Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> std::result::Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
@@ -120,46 +323,52 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_unit_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
     ) -> std::result::Result<Self::Ok, Self::Error> {
         self.serialize_unit()
     }
 
     fn serialize_unit_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
     ) -> std::result::Result<Self::Ok, Self::Error> {
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.output += "{";
-        name.serialize(&mut *self)?;
-        self.output += ":";
         variant.serialize(&mut *self)?;
+        self.output += ":";
+        value.serialize(&mut *self)?;
+        self.output += "}";
         Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.output += "[";
+        // Room for a separator and a few digits per element up front, so a
+        // long numeric slice is written without regrowing the output.
+        if let Some(len) = len {
+            self.output.reserve(len.saturating_mul(4));
+        }
+        self.output.push('[');
         Ok(self)
     }
 
@@ -169,7 +378,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_tuple_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
         self.serialize_seq(Some(len))
@@ -177,25 +386,25 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.output += "}";
+        self.output += "{";
         variant.serialize(&mut *self)?;
         self.output += ":[";
         Ok(self)
     }
 
-    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.output += "{";
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.begin_object();
         Ok(self)
     }
 
     fn serialize_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
         len: usize,
     ) -> std::result::Result<Self::SerializeStruct, Self::Error> {
         self.serialize_map(Some(len))
@@ -203,29 +412,30 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_struct_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         self.output += "{";
         variant.serialize(&mut *self)?;
-        self.output += ":{";
+        self.output += ":";
+        self.begin_object();
         Ok(self)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl ser::SerializeSeq for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if !self.output.ends_with("[") {
-            self.output += ",";
+        if !self.output.ends_with('[') {
+            self.output.push(',');
         }
         value.serialize(&mut **self)
     }
@@ -236,14 +446,14 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl ser::SerializeTuple for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         if !self.output.ends_with("[") {
             self.output += ",";
@@ -257,14 +467,14 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl ser::SerializeTupleStruct for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         if !self.output.ends_with("[") {
             self.output += ",";
@@ -278,14 +488,14 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl ser::SerializeTupleVariant for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         if !self.output.ends_with("[") {
             self.output += ",";
@@ -299,82 +509,479 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl ser::SerializeMap for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if !self.output.ends_with("{") {
-            self.output += ",";
-        }
-        key.serialize(&mut **self)?;
+        let start = self.begin_member();
+        key.serialize(MapKeySerializer { ser: self })?;
+        self.key_written(start)?;
         self.output += ":";
         Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.end_object();
         self.output += "}";
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut Serializer {
+impl ser::SerializeStruct for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if !self.output.ends_with("{") {
-            self.output += ",";
-        }
+        let start = self.begin_member();
         key.serialize(&mut **self)?;
+        self.key_written(start)?;
         self.output += ":";
         value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.end_object();
         self.output += "}";
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl ser::SerializeStructVariant for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if !self.output.ends_with("{") {
-            self.output += ",";
-        }
+        let start = self.begin_member();
         key.serialize(&mut **self)?;
+        self.key_written(start)?;
         self.output += ":";
         value.serialize(&mut **self)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+        self.end_object();
         self.output += "}}";
         Ok(())
     }
 }
 
+const DIGIT_PAIRS: &[u8; 200] = b"\
+    0001020304050607080910111213141516171819\
+    2021222324252627282930313233343536373839\
+    4041424344454647484950515253545556575859\
+    6061626364656667686970717273747576777879\
+    8081828384858687888990919293949596979899";
+
+/// Appends `v` with the quote, the backslash and control chars escaped,
+/// which is all JSON requires. Plain runs are copied whole.
+fn escape_str(output: &mut String, v: &str) {
+    let mut run = 0;
+    for (i, b) in v.bytes().enumerate() {
+        let escape = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0..=0x1f => "",
+            _ => continue,
+        };
+        output.push_str(&v[run..i]);
+        match escape {
+            "" => {
+                let _ = write!(output, "\\u{:04x}", b);
+            }
+            escape => output.push_str(escape),
+        }
+        run = i + 1;
+    }
+    output.push_str(&v[run..]);
+}
+
+/// Writes the decimal digits of `v` to the end of `buf`, two at a time, and
+/// returns them. Twenty bytes hold `u64::MAX`.
+fn format_u64(buf: &mut [u8; 20], mut v: u64) -> &str {
+    let mut pos = buf.len();
+    while v >= 100 {
+        let pair = (v % 100) as usize * 2;
+        v /= 100;
+        pos -= 2;
+        buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    }
+    if v >= 10 {
+        let pair = v as usize * 2;
+        pos -= 2;
+        buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    } else {
+        pos -= 1;
+        buf[pos] = b'0' + v as u8;
+    }
+    // Only ASCII digits were written.
+    std::str::from_utf8(&buf[pos..]).unwrap_or_default()
+}
+
+/// The largest integer a double holds exactly, 2^53.
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// Appends `v` the way ECMAScript's `Number.prototype.toString` does, as
+/// RFC 8785 requires: the shortest digits that round-trip, plain for
+/// magnitudes from 1e-7 up to 1e21 and in exponent form otherwise.
+fn write_es_number(output: &mut String, v: f64) {
+    if v == 0.0 {
+        // Negative zero too.
+        output.push('0');
+        return;
+    }
+    if v < 0.0 {
+        output.push('-');
+    }
+    // `LowerExp` prints the same shortest digits as `d.ddde-n`.
+    let exp = format!("{:e}", v.abs());
+    let (mantissa, exponent) = exp.split_once('e').unwrap_or((&exp, "0"));
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    // The value is `0.digits` times ten to the `n`.
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+    if k <= n && n <= 21 {
+        *output += &digits;
+        output.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        let _ = write!(output, "{}.{}", int, frac);
+    } else if -6 < n && n <= 0 {
+        *output += "0.";
+        output.extend(std::iter::repeat_n('0', -n as usize));
+        *output += &digits;
+    } else {
+        let (first, rest) = digits.split_at(1);
+        *output += first;
+        if !rest.is_empty() {
+            output.push('.');
+            *output += rest;
+        }
+        let sign = if n > 0 { '+' } else { '-' };
+        let _ = write!(output, "e{}{}", sign, (n - 1).abs());
+    }
+}
+
+/// Serializes the keys of a `SerializeMap`. JSON object keys must be strings,
+/// so integers, bools, chars, finite floats and unit variants are written as
+/// quoted strings, mirroring what `deserialize_parsed_key!` accepts on the
+/// read side.
+struct MapKeySerializer<'a> {
+    ser: &'a mut Serializer,
+}
+
+impl MapKeySerializer<'_> {
+    fn quoted<F>(self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Serializer) -> Result<()>,
+    {
+        self.ser.output += "\"";
+        f(self.ser)?;
+        self.ser.output += "\"";
+        Ok(())
+    }
+}
+
+impl ser::Serializer for MapKeySerializer<'_> {
+    type Ok = ();
+
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+
+    type SerializeTuple = Impossible<(), Error>;
+
+    type SerializeTupleStruct = Impossible<(), Error>;
+
+    type SerializeTupleVariant = Impossible<(), Error>;
+
+    type SerializeMap = Impossible<(), Error>;
+
+    type SerializeStruct = Impossible<(), Error>;
+
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.quoted(|ser| ser::Serializer::serialize_bool(ser, v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        // Keys are strings, so even canonical mode keeps every digit.
+        self.quoted(|ser| {
+            if v < 0 {
+                ser.output.push('-');
+            }
+            ser.output += format_u64(&mut [0; 20], v.unsigned_abs());
+            Ok(())
+        })
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.quoted(|ser| {
+            ser.output += format_u64(&mut [0; 20], v);
+            Ok(())
+        })
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        if !v.is_finite() {
+            return Err(Error::InvalidMapKey("non-finite float"));
+        }
+        self.quoted(|ser| ser::Serializer::serialize_f32(ser, v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        if !v.is_finite() {
+            return Err(Error::InvalidMapKey("non-finite float"));
+        }
+        self.quoted(|ser| ser::Serializer::serialize_f64(ser, v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        ser::Serializer::serialize_char(self.ser, v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        ser::Serializer::serialize_str(self.ser, v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(Error::InvalidMapKey("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::InvalidMapKey("none"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(Error::InvalidMapKey("unit"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(Error::InvalidMapKey("unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidMapKey("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::InvalidMapKey("sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::InvalidMapKey("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::InvalidMapKey("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::InvalidMapKey("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::InvalidMapKey("map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::InvalidMapKey("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::InvalidMapKey("struct variant"))
+    }
+}
+
+#[test]
+fn test_map_key() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    let ints: BTreeMap<u32, u8> = [(1, 2), (3, 4)].into_iter().collect();
+    assert_eq!(to_string(&ints).unwrap(), r#"{"1":2,"3":4}"#);
+
+    let bools: BTreeMap<bool, u8> = [(false, 0), (true, 1)].into_iter().collect();
+    assert_eq!(to_string(&bools).unwrap(), r#"{"false":0,"true":1}"#);
+
+    let chars: BTreeMap<char, u8> = [('a', 1)].into_iter().collect();
+    assert_eq!(to_string(&chars).unwrap(), r#"{"a":1}"#);
+
+    let colors: BTreeMap<Color, u8> = [(Color::Red, 1), (Color::Green, 2)].into_iter().collect();
+    assert_eq!(to_string(&colors).unwrap(), r#"{"Red":1,"Green":2}"#);
+
+    let seqs: BTreeMap<Vec<u8>, u8> = [(vec![1], 1)].into_iter().collect();
+    assert_eq!(to_string(&seqs), Err(Error::InvalidMapKey("sequence")));
+
+    struct FloatKeys(f64);
+    impl Serialize for FloatKeys {
+        fn serialize<S: ser::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.collect_map([(self.0, 1u8)])
+        }
+    }
+    assert_eq!(to_string(&FloatKeys(1.5)).unwrap(), r#"{"1.5":1}"#);
+    assert_eq!(to_string(&FloatKeys(-2.0)).unwrap(), r#"{"-2.0":1}"#);
+    assert_eq!(
+        to_string(&FloatKeys(f64::NAN)),
+        Err(Error::InvalidMapKey("non-finite float"))
+    );
+}
+
+#[test]
+fn test_integer() {
+    for v in [0, 7, 10, 99, 100, 12345, -1, -100, i64::MAX, i64::MIN] {
+        assert_eq!(to_string(&v).unwrap(), v.to_string());
+    }
+    for v in [9u64, 1_000_000, u64::MAX] {
+        assert_eq!(to_string(&v).unwrap(), v.to_string());
+    }
+}
+
+#[test]
+fn test_float() {
+    assert_eq!(to_string(&0.1f32).unwrap(), "0.1");
+    assert_eq!(to_string(&0.1f64).unwrap(), "0.1");
+    assert_eq!(to_string(&1.0f64).unwrap(), "1.0");
+    assert_eq!(to_string(&-0.0f32).unwrap(), "-0.0");
+    assert_eq!(to_string(&1e300f64).unwrap(), "1e300");
+    assert_eq!(to_string(&f32::MAX).unwrap(), "3.4028235e38");
+
+    assert_eq!(to_string(&f64::NAN), Err(Error::NonFiniteFloat("NaN")));
+    let floats = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY];
+    let mut ser = Serializer::new().non_finite_float(NonFiniteFloat::Null);
+    floats.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_string(), "[null,null,null]");
+    let mut ser = Serializer::new().non_finite_float(NonFiniteFloat::String);
+    floats.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_string(), r#"["NaN","Infinity","-Infinity"]"#);
+}
+
+#[test]
+fn test_newtype_variant() {
+    #[derive(Serialize)]
+    enum Shape {
+        Circle(u32),
+    }
+    assert_eq!(to_string(&Shape::Circle(2)).unwrap(), r#"{"Circle":2}"#);
+}
+
+#[test]
+fn test_tuple_variant() {
+    #[derive(Serialize)]
+    enum Shape {
+        Rect(u32, u32),
+    }
+    assert_eq!(to_string(&Shape::Rect(2, 3)).unwrap(), r#"{"Rect":[2,3]}"#);
+}
+
+#[test]
+fn test_u64_max() {
+    assert_eq!(to_string(&u64::MAX).unwrap(), "18446744073709551615");
+    assert_eq!(to_string(&(1u64 << 63)).unwrap(), "9223372036854775808");
+}
+
 #[test]
 fn test_to_string() {
     #[derive(Serialize)]
@@ -395,3 +1002,104 @@ fn test_to_string() {
         Err(e) => println!("err is {}", e),
     }
 }
+
+#[test]
+fn test_numeric_seq() {
+    assert_eq!(to_string(&[1i64, -20, 300]).unwrap(), "[1,-20,300]");
+    assert_eq!(to_string(&vec![0.5f64, -1.0]).unwrap(), "[0.5,-1.0]");
+    assert_eq!(to_string(&Vec::<u8>::new()).unwrap(), "[]");
+
+    // The bulk writers match the serde path.
+    let floats = [0.5, -1.0, 1e300, -0.0];
+    let mut ser = Serializer::new();
+    ser.write_f64_array(&floats).unwrap();
+    assert_eq!(ser.into_string(), to_string(&floats).unwrap());
+    let ints = [1, -20, i64::MIN, i64::MAX];
+    let mut ser = Serializer::new().canonical(true);
+    ser.write_i64_array(&ints).unwrap();
+    assert_eq!(ser.into_string(), to_canonical_string(&ints).unwrap());
+    let mut ser = Serializer::new();
+    assert_eq!(
+        ser.write_f64_array(&[f64::NAN]),
+        Err(Error::NonFiniteFloat("NaN"))
+    );
+    let mut ser = Serializer::new().non_finite_float(NonFiniteFloat::Null);
+    ser.write_f64_array(&[]).unwrap();
+    ser.write_f64_array(&[f64::INFINITY]).unwrap();
+    assert_eq!(ser.into_string(), "[][null]");
+}
+
+#[test]
+fn test_escape_str() {
+    assert_eq!(
+        to_string(&"a\"b\\c\n\u{1}\u{e9}/").unwrap(),
+        "\"a\\\"b\\\\c\\n\\u0001\u{e9}/\""
+    );
+    let map: std::collections::BTreeMap<&str, char> = [("k\"", '\t')].into_iter().collect();
+    assert_eq!(to_string(&map).unwrap(), r#"{"k\"":"\t"}"#);
+}
+
+#[test]
+fn test_canonical() {
+    let number = |v: f64| to_canonical_string(&v).unwrap();
+    assert_eq!(number(-0.0), "0");
+    assert_eq!(number(4.50), "4.5");
+    assert_eq!(number(2e-3), "0.002");
+    assert_eq!(number(0.000001), "0.000001");
+    assert_eq!(number(1e-7), "1e-7");
+    assert_eq!(number(1e-27), "1e-27");
+    assert_eq!(number(1e20), "100000000000000000000");
+    assert_eq!(number(1e21), "1e+21");
+    assert_eq!(number(1e9 / 3.0), "333333333.3333333");
+    assert_eq!(number(5e-324), "5e-324");
+    assert_eq!(number(-1.7976931348623157e308), "-1.7976931348623157e+308");
+    assert!(to_canonical_string(&f64::NAN).is_err());
+    assert_eq!(
+        to_canonical_string(&9007199254740993u64).unwrap(),
+        "9007199254740992"
+    );
+    assert_eq!(to_canonical_string(&-42i8).unwrap(), "-42");
+
+    // The key order of RFC 8785 section 3.2.3: `\u{1f600}` is the surrogate
+    // pair d83d de00, so it sorts before `\u{fb33}`.
+    let map: std::collections::HashMap<&str, u8> = [
+        ("\u{20ac}", 1),
+        ("\r", 2),
+        ("\u{fb33}", 3),
+        ("1", 4),
+        ("\u{1f600}", 5),
+        ("\u{80}", 6),
+        ("\u{f6}", 7),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(
+        to_canonical_string(&map).unwrap(),
+        "{\"\\r\":2,\"1\":4,\"\u{80}\":6,\"\u{f6}\":7,\"\u{20ac}\":1,\"\u{1f600}\":5,\"\u{fb33}\":3}"
+    );
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f32, center: (i32, i32) },
+    }
+    #[derive(Serialize)]
+    struct Drawing {
+        title: &'static str,
+        shapes: Vec<Shape>,
+        layers: std::collections::BTreeMap<u64, bool>,
+    }
+    let drawing = Drawing {
+        title: "a\u{1}",
+        shapes: vec![Shape::Circle {
+            radius: 1.5,
+            center: (0, -2),
+        }],
+        layers: [(10, true), (9, false), (u64::MAX, true)]
+            .into_iter()
+            .collect(),
+    };
+    assert_eq!(
+        to_canonical_string(&drawing).unwrap(),
+        r#"{"layers":{"10":true,"18446744073709551615":true,"9":false},"shapes":[{"Circle":{"center":[0,-2],"radius":1.5}}],"title":"a\u0001"}"#
+    );
+}
@@ -0,0 +1,481 @@
+//! Streaming extraction of the values at a few paths from input of any size.
+//!
+//! A [`StreamSelector`] reads from an [`io::Read`] through a buffer that the
+//! [`Tokenizer`] lexes one token at a time, and keeps only the path to the
+//! current value. Containers on the way to a pattern are walked, everything
+//! else is skipped with the tokenizer's skip without being kept, and a
+//! matched value is copied out whole. Memory use is the buffer, the current
+//! path and the largest matched value; the buffer grows past its 8 KiB only
+//! to hold a single string or number longer than that.
+
+use crate::error::Error;
+use crate::pointer;
+use crate::token::{MaybeString, Token};
+use crate::tokenizer::{Brackets, Result, Tokenizer};
+use serde::de;
+use std::io;
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// One value found at a pattern.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    /// The JSON Pointer of the value, with wildcards filled in.
+    pub pointer: String,
+    /// The source text of the value.
+    pub raw: String,
+}
+
+impl Match {
+    pub fn deserialize<'a, T>(&'a self) -> Result<T>
+    where
+        T: de::Deserialize<'a>,
+    {
+        crate::de::from_str(&self.raw)
+    }
+}
+
+/// Yields the values whose path matches one of a set of patterns, as each
+/// one ends. Patterns are JSON Pointers in which a `*` reference token
+/// matches any member name or array index. A value inside a matched value is
+/// part of that match, not a match of its own. The input may hold several
+/// documents separated by whitespace, as in JSON Lines.
+pub struct StreamSelector<R> {
+    input: Input<R>,
+    patterns: Vec<Vec<String>>,
+    /// Whether each open container is an object, and its current index.
+    frames: Vec<(bool, usize)>,
+    /// The reference tokens down to the current value.
+    path: Vec<String>,
+    /// At the start of a value, rather than just after one.
+    at_value: bool,
+    done: bool,
+}
+
+impl<R: io::Read> StreamSelector<R> {
+    pub fn new(reader: R, patterns: &[&str]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| pointer::parse(pattern))
+            .collect::<Result<_>>()?;
+        Ok(StreamSelector {
+            input: Input::new(reader),
+            patterns,
+            frames: Vec::new(),
+            path: Vec::new(),
+            at_value: true,
+            done: false,
+        })
+    }
+
+    fn matches(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.len() == self.path.len() && segments_match(pattern, &self.path))
+    }
+
+    /// Whether a pattern could match something inside the current value.
+    fn leads_to_match(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.len() > self.path.len() && segments_match(pattern, &self.path))
+    }
+
+    fn pointer(&self) -> String {
+        self.path
+            .iter()
+            .map(|token| format!("/{}", pointer::escape(token)))
+            .collect()
+    }
+
+    fn step(&mut self) -> Result<Option<Match>> {
+        if self.at_value {
+            self.at_value = false;
+            if self.matches() {
+                let raw = self.input.value(true)?.unwrap_or_default();
+                let pointer = self.pointer();
+                return Ok(Some(Match { pointer, raw }));
+            }
+            if self.leads_to_match() {
+                if let Some(open @ (b'{' | b'[')) = self.input.peek_token()? {
+                    self.input.bump();
+                    let object = open == b'{';
+                    self.frames.push((object, 0));
+                    let close = if object { b'}' } else { b']' };
+                    if self.input.peek_token()? == Some(close) {
+                        self.input.bump();
+                        self.frames.pop();
+                    } else {
+                        self.enter_member(object, 0)?;
+                    }
+                    return Ok(None);
+                }
+            }
+            self.input.value(false)?;
+            return Ok(None);
+        }
+        let (object, index) = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => {
+                // Another document, or the end of the input.
+                match self.input.peek_token()? {
+                    Some(_) => self.at_value = true,
+                    None => self.done = true,
+                }
+                return Ok(None);
+            }
+        };
+        *index += 1;
+        let (object, index) = (*object, *index);
+        self.path.pop();
+        let close = if object { b'}' } else { b']' };
+        match self.input.peek_token()? {
+            Some(b',') => {
+                self.input.bump();
+                self.enter_member(object, index)?;
+            }
+            Some(b) if b == close => {
+                self.input.bump();
+                self.frames.pop();
+            }
+            Some(b) => return Err(Error::Unexpected(self.input.pos(), b as char)),
+            None => return Err(Error::EofWhileParsingValue(self.input.pos())),
+        }
+        Ok(None)
+    }
+
+    /// Moves to the value of the next member or element, pushing its key or
+    /// index onto the path.
+    fn enter_member(&mut self, object: bool, index: usize) -> Result<()> {
+        if !object {
+            self.path.push(index.to_string());
+        } else {
+            let key = self.input.key()?;
+            self.path.push(key);
+            match self.input.peek_token()? {
+                Some(b':') => self.input.bump(),
+                Some(b) => return Err(Error::Unexpected(self.input.pos(), b as char)),
+                None => return Err(Error::EofWhileParsingValue(self.input.pos())),
+            }
+        }
+        self.at_value = true;
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Iterator for StreamSelector<R> {
+    type Item = Result<Match>;
+
+    fn next(&mut self) -> Option<Result<Match>> {
+        while !self.done {
+            match self.step() {
+                Ok(Some(found)) => return Some(Ok(found)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn segments_match(pattern: &[String], path: &[String]) -> bool {
+    pattern
+        .iter()
+        .zip(path)
+        .all(|(want, have)| want == "*" || want == have)
+}
+
+/// A window over a reader that the tokenizer lexes one token at a time.
+/// The window is refilled when the next token might run past its end, and
+/// grows when a single token does not fit.
+struct Input<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// The start of the next token in `buf`.
+    start: usize,
+    /// The bytes read into `buf`.
+    len: usize,
+    /// The bytes of `buf` known to be valid UTF-8.
+    valid: usize,
+    /// Where a value being copied out starts; kept when the window moves.
+    mark: Option<usize>,
+    /// How far the token at `start` has been scanned without finding its
+    /// end, and whether that stopped after a backslash.
+    scanned: Option<(usize, bool)>,
+    /// The offset in the whole input of `buf[0]`.
+    offset: usize,
+    /// The bytes after `valid` are not UTF-8, as opposed to cut short.
+    invalid: bool,
+    eof: bool,
+}
+
+impl<R: io::Read> Input<R> {
+    fn new(reader: R) -> Self {
+        Input {
+            reader,
+            buf: vec![0; BUFFER_SIZE],
+            start: 0,
+            len: 0,
+            valid: 0,
+            mark: None,
+            scanned: None,
+            offset: 0,
+            invalid: false,
+            eof: false,
+        }
+    }
+
+    /// The offset in the whole input of the next token.
+    fn pos(&self) -> usize {
+        self.offset + self.start
+    }
+
+    /// Drops the bytes before `start`, or before a value being copied,
+    /// and reads more, growing the buffer if it is full.
+    fn fill(&mut self) -> Result<()> {
+        let keep = self.mark.unwrap_or(self.start);
+        if keep > 0 {
+            self.buf.copy_within(keep..self.len, 0);
+            self.offset += keep;
+            self.start -= keep;
+            self.len -= keep;
+            self.valid -= keep;
+            self.mark = self.mark.map(|_| 0);
+            self.scanned = self.scanned.map(|(at, escaped)| (at - keep, escaped));
+        }
+        if self.len == self.buf.len() {
+            self.buf.resize(self.buf.len() * 2, 0);
+        }
+        let n = loop {
+            match self.reader.read(&mut self.buf[self.len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Io(e.to_string())),
+            }
+        };
+        self.len += n;
+        self.eof = n == 0;
+        match std::str::from_utf8(&self.buf[self.valid..self.len]) {
+            Ok(_) => self.valid = self.len,
+            Err(e) => {
+                self.valid += e.valid_up_to();
+                self.invalid = e.error_len().is_some();
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips whitespace and makes sure the window holds the whole of the
+    /// next token, and the byte after it if that is what ends the token.
+    /// Returns the token's first byte without consuming it, or `None` at the
+    /// end of the input.
+    fn peek_token(&mut self) -> Result<Option<u8>> {
+        loop {
+            // A byte order mark may start the input, as for `Tokenizer::new`.
+            if self.pos() == 0 && self.buf[..self.valid].starts_with("\u{feff}".as_bytes()) {
+                self.start = '\u{feff}'.len_utf8();
+            }
+            while self.start < self.valid && crate::scan::is_whitespace(self.buf[self.start]) {
+                self.start += 1;
+                self.scanned = None;
+            }
+            let first = self.buf[..self.valid].get(self.start).copied();
+            // Only the bytes read since the last look are scanned for the end.
+            let (from, mut escaped) = self.scanned.unwrap_or((self.start + 1, false));
+            let rest = &self.buf[from.min(self.valid)..self.valid];
+            let complete = match first {
+                None => false,
+                Some(b'"') => rest.iter().any(|&b| {
+                    let end = b == b'"' && !escaped;
+                    escaped = b == b'\\' && !escaped;
+                    end
+                }),
+                Some(b) if b.is_ascii_alphanumeric() || b == b'-' => rest
+                    .iter()
+                    .any(|&b| !b.is_ascii_alphanumeric() && !matches!(b, b'-' | b'+' | b'.')),
+                Some(_) => true,
+            };
+            if complete || (self.eof && self.valid == self.len) {
+                return Ok(first);
+            }
+            if first.is_some() {
+                self.scanned = Some((self.valid, escaped));
+            }
+            if self.invalid || self.eof {
+                return Err(Error::InvalidUtf8(self.offset + self.valid));
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Runs `f` on a tokenizer over the window, which must hold the next
+    /// token whole, and moves past what it consumed.
+    fn lex<T>(&mut self, f: impl FnOnce(&mut Tokenizer) -> Result<T>) -> Result<T> {
+        let at = self.pos();
+        // SAFETY: `start` is on a char boundary and `buf[..valid]` has been
+        // checked to be UTF-8.
+        let text = unsafe { std::str::from_utf8_unchecked(&self.buf[self.start..self.valid]) };
+        if at > 0 && text.starts_with('\u{feff}') {
+            // The tokenizer would take it for a byte order mark.
+            return Err(Error::NotSupportedChar('\u{feff}', at));
+        }
+        let mut tokenizer = Tokenizer::new(text);
+        let result = f(&mut tokenizer).map_err(|e| e.shifted(at));
+        self.start += tokenizer.pos();
+        self.scanned = None;
+        result
+    }
+
+    /// Consumes the punctuation `peek_token` returned.
+    fn bump(&mut self) {
+        self.start += 1;
+        self.scanned = None;
+    }
+
+    /// Reads one value with the tokenizer's skip, which checks its tokens
+    /// and that its brackets pair up, and returns its text if `copy`.
+    fn value(&mut self, copy: bool) -> Result<Option<String>> {
+        if self.peek_token()?.is_none() {
+            return Err(Error::EofWhileParsingValue(self.pos()));
+        }
+        self.mark = copy.then_some(self.start);
+        let mut brackets = Brackets::default();
+        let skipped = loop {
+            if self.peek_token()?.is_none() {
+                break Err(Error::EofWhileParsingValue(self.pos()));
+            }
+            if let Err(e) = self.lex(|t| t.skip_token(&mut brackets)) {
+                break Err(e);
+            }
+            if brackets.is_empty() {
+                break Ok(());
+            }
+        };
+        let mark = self.mark.take();
+        skipped?;
+        // Whitespace inside a copied value is kept as it was.
+        let raw = match mark {
+            Some(mark) => &self.buf[mark..self.start],
+            None => return Ok(None),
+        };
+        match std::str::from_utf8(raw) {
+            Ok(raw) => Ok(Some(raw.to_string())),
+            Err(_) => Err(Error::InvalidUtf8(self.offset + self.start)),
+        }
+    }
+
+    /// Reads a member name, unescaped.
+    fn key(&mut self) -> Result<String> {
+        match self.peek_token()? {
+            Some(b'"') => {}
+            Some(_) => return Err(Error::JSONKeyMustBeString()),
+            None => return Err(Error::EofWhileParsingValue(self.pos())),
+        }
+        self.lex(|t| match t.next()? {
+            Token::String(MaybeString::Escaped(key), _) => Ok(key),
+            Token::String(MaybeString::NotEscaped(key), _) => Ok(key.to_string()),
+            _ => Err(Error::JSONKeyMustBeString()),
+        })
+    }
+}
+
+#[test]
+fn test_stream_selector() {
+    /// Hands out a few bytes per read, so values straddle refills.
+    struct Trickle<'a>(&'a [u8]);
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let input = r#"{"meta": {"user": {"id": 0}}, "events": [
+        {"user": {"id": 1, "name": "a"}, "skip": [{"user": {"id": -1}}]},
+        {"kind": "x"},
+        {"user": {"id": "two\"]"}, "user": 3}
+    ]}
+    {"events": [{"user": {"id": [4, 5]}}]}"#;
+    let found: Vec<Match> = StreamSelector::new(Trickle(input.as_bytes()), &["/events/*/user/id"])
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    let found: Vec<(&str, &str)> = found
+        .iter()
+        .map(|m| (m.pointer.as_str(), m.raw.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("/events/0/user/id", "1"),
+            ("/events/2/user/id", r#""two\"]""#),
+            ("/events/0/user/id", "[4, 5]"),
+        ]
+    );
+
+    let mut selector = StreamSelector::new(input.as_bytes(), &["/events/2/user"]).unwrap();
+    let user = selector.next().unwrap().unwrap();
+    assert_eq!(user.pointer, "/events/2/user");
+    assert_eq!(
+        user.deserialize::<crate::value::Value>()
+            .unwrap()
+            .pointer("/id")
+            .and_then(|v| v.as_str()),
+        Some("two\"]")
+    );
+
+    let mut selector = StreamSelector::new(&b"{\"a\": [1, 2}"[..], &["/a/*"]).unwrap();
+    assert_eq!(selector.next().unwrap().unwrap().raw, "1");
+    assert_eq!(selector.next().unwrap().unwrap().raw, "2");
+    assert_eq!(selector.next(), Some(Err(Error::Unexpected(11, '}'))));
+    assert_eq!(selector.next(), None);
+
+    // Tokens longer than the buffer are read whole, and errors past the
+    // first buffer report their offset in the whole input.
+    let long = "x".repeat(3 * BUFFER_SIZE);
+    let input =
+        format!(r#"[{{"skip": "{long}", "id": 1.5e1}}, {{"id": "{long}"}}, {{"id": [1}}]}}]"#);
+    let mut selector = StreamSelector::new(Trickle(input.as_bytes()), &["/*/id"]).unwrap();
+    assert_eq!(selector.next().unwrap().unwrap().raw, "1.5e1");
+    assert_eq!(
+        selector.next().unwrap().unwrap().raw,
+        format!(r#""{long}""#)
+    );
+    let at = input.find("[1}").unwrap() + 2;
+    assert_eq!(selector.next(), Some(Err(Error::Unexpected(at, '}'))));
+
+    // Skipped values are checked by the tokenizer.
+    let first = |input: &str| {
+        StreamSelector::new(input.as_bytes(), &["/b"])
+            .unwrap()
+            .next()
+    };
+    assert_eq!(
+        first(r#"{"a": [}, "b": 1}"#),
+        Some(Err(Error::Unexpected(7, '}')))
+    );
+    assert!(first(r#"{"a": tru, "b": 1}"#).unwrap().is_err());
+    assert!(first(r#"{"a": 01, "b": 1}"#).unwrap().is_err());
+    assert_eq!(
+        first("{\"a\": \"\u{e9}\", \"b\": 1}").unwrap().unwrap().raw,
+        "1"
+    );
+    let bad_utf8 = StreamSelector::new(&b"{\"a\": \"\xff\", \"b\": 1}"[..], &["/b"]);
+    assert_eq!(bad_utf8.unwrap().next(), Some(Err(Error::InvalidUtf8(7))));
+
+    // A byte order mark is skipped at the start only.
+    let select = |input: &str, pattern: &str| {
+        StreamSelector::new(Trickle(input.as_bytes()), &[pattern])
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .map(|found| found.into_iter().map(|m| m.raw).collect::<Vec<_>>())
+    };
+    assert_eq!(select("\u{feff}{\"a\":1}", "/a").unwrap(), ["1"]);
+    assert_eq!(select("\u{feff}[1]", "/0").unwrap(), ["1"]);
+    assert!(select("[1] \u{feff}[2]", "/0").is_err());
+}
@@ -1,61 +1,86 @@
+use crate::float::Decimal;
 use crate::tokenizer::Result;
 use serde::de;
 
+/// A structural character. The discriminant is the byte itself, so matching
+/// punctuation is a single byte compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Punct {
+    LeftBracket = b'[',
+    RightBracket = b']',
+    LeftCurly = b'{',
+    RightCurly = b'}',
+    Colon = b':',
+    Comma = b',',
+}
+
+impl Punct {
+    pub fn from_byte(b: u8) -> Option<Punct> {
+        match b {
+            b'[' => Some(Punct::LeftBracket),
+            b']' => Some(Punct::RightBracket),
+            b'{' => Some(Punct::LeftCurly),
+            b'}' => Some(Punct::RightCurly),
+            b':' => Some(Punct::Colon),
+            b',' => Some(Punct::Comma),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Token<'a> {
-    Operator(&'a str, (usize, usize)),
-    Bracket(&'a str, (usize, usize)),
+    Punct(Punct, (usize, usize)),
 
     Null((usize, usize)),
     Bool(bool, (usize, usize)),
 
     String(MaybeString<'a>, (usize, usize)),
 
-    Number(ParseNumber, (usize, usize)),
+    Number(ParseNumber<'a>, (usize, usize)),
 
-    EOF,
+    Eof,
 }
 
-impl<'a> Token<'a> {
+impl Token<'_> {
     pub fn is_left_bracket(&self) -> bool {
-        self.checkOp("[")
+        self.is_punct(Punct::LeftBracket)
     }
 
     pub fn is_right_bracket(&self) -> bool {
-        self.checkOp("]")
+        self.is_punct(Punct::RightBracket)
     }
 
     pub fn is_left_curly(&self) -> bool {
-        self.checkOp("{")
+        self.is_punct(Punct::LeftCurly)
     }
 
     pub fn is_right_curly(&self) -> bool {
-        self.checkOp("}")
+        self.is_punct(Punct::RightCurly)
     }
 
     pub fn is_colon(&self) -> bool {
-        self.checkOp(":")
+        self.is_punct(Punct::Colon)
     }
 
     pub fn is_comma(&self) -> bool {
-        self.checkOp(",")
+        self.is_punct(Punct::Comma)
     }
 
-    pub fn checkOp(&self, op: &str) -> bool {
+    pub fn is_punct(&self, punct: Punct) -> bool {
+        matches!(self, Token::Punct(p, _) if *p == punct)
+    }
+
+    pub fn span(&self) -> Option<(usize, usize)> {
         match self {
-            Token::Bracket(s, _) => {
-                if *s == op {
-                    return true;
-                }
-            }
-            Token::Operator(s, _) => {
-                if *s == op {
-                    return true;
-                }
-            }
-            _ => return false,
+            Token::Punct(_, span)
+            | Token::Null(span)
+            | Token::Bool(_, span)
+            | Token::String(_, span)
+            | Token::Number(_, span) => Some(*span),
+            Token::Eof => None,
         }
-        false
     }
 }
 
@@ -65,20 +90,57 @@ pub enum MaybeString<'a> {
     Escaped(String),
 }
 
-#[derive(Debug)]
-pub enum ParseNumber {
+impl MaybeString<'_> {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MaybeString::NotEscaped(s) => s,
+            MaybeString::Escaped(s) => s,
+        }
+    }
+}
+
+/// A number literal, split into its parts while lexing but not yet
+/// converted, so floats are rounded once to the width the visitor wants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseNumber<'a> {
+    /// A non-negative integer literal that fits in `u64`.
+    U64(u64),
+    /// A negative integer literal that fits in `i64`.
     I64(i64),
-    F64(f64),
+    /// Anything with a fraction or exponent, or an integer too large for
+    /// 64 bits.
+    Float(Decimal<'a>),
 }
 
-impl ParseNumber {
+impl ParseNumber<'_> {
     pub fn visit<'de, V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
         match self {
-            Self::F64(num) => visitor.visit_f64(num),
+            Self::U64(num) => visitor.visit_u64(num),
             Self::I64(num) => visitor.visit_i64(num),
+            Self::Float(num) => visitor.visit_f64(num.to_f64()?),
+        }
+    }
+
+    pub fn to_f64(self) -> Result<f64> {
+        match self {
+            Self::U64(num) => Ok(num as f64),
+            // Non-negative literals are `U64`, so a zero here was `-0`.
+            Self::I64(0) => Ok(-0.0),
+            Self::I64(num) => Ok(num as f64),
+            Self::Float(num) => num.to_f64(),
+        }
+    }
+
+    /// Rounds straight to `f32`; going through `f64` first would round twice.
+    pub fn to_f32(self) -> Result<f32> {
+        match self {
+            Self::U64(num) => Ok(num as f32),
+            Self::I64(0) => Ok(-0.0),
+            Self::I64(num) => Ok(num as f32),
+            Self::Float(num) => num.to_f32(),
         }
     }
 }
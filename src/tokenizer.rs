@@ -1,273 +1,543 @@
 use crate::error::Error;
-use crate::token::{MaybeString, ParseNumber, Token};
+use crate::float;
+use crate::scan;
+use crate::token::{MaybeString, ParseNumber, Punct, Token};
 use core::result;
+use std::marker::PhantomData;
+use std::slice;
 use std::str;
 use std::string::String;
 
 pub type Result<T> = result::Result<T, Error>;
 
-#[derive(Clone)]
+/// The containers open around a value being skipped, innermost last, as one
+/// bit each that is set for an object. Nesting up to 64 deep needs no
+/// allocation.
+#[derive(Default)]
+pub(crate) struct Brackets {
+    depth: usize,
+    bits: u64,
+    deeper: Vec<bool>,
+}
+
+impl Brackets {
+    fn push(&mut self, object: bool) {
+        if self.depth < 64 {
+            self.bits = (self.bits & !(1 << self.depth)) | ((object as u64) << self.depth);
+        } else {
+            self.deeper.push(object);
+        }
+        self.depth += 1;
+    }
+
+    /// Whether the innermost container is an object, closing it.
+    fn pop(&mut self) -> Option<bool> {
+        if self.depth == 0 {
+            return None;
+        }
+        self.depth -= 1;
+        if self.depth < 64 {
+            Some(self.bits >> self.depth & 1 == 1)
+        } else {
+            self.deeper.pop()
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.depth == 0
+    }
+}
+
+/// Splits the input into tokens. Scans bytes by position: every structural
+/// byte is ASCII, and ASCII bytes never occur inside a multi-byte UTF-8
+/// sequence, so chars only need decoding for error reports.
+///
+/// The input is held as a raw pointer rather than a `&'a str`: a tokenizer
+/// made for [`Deserializer::from_mut_str`] writes unescaped strings back into
+/// the buffer, and a reference spanning the whole input would alias those
+/// writes. Views of the input are only made for as long as one scan needs
+/// them, and the `&'a str`s handed out in tokens cover bytes behind the
+/// position, which are never written again.
+///
+/// A tokenizer that writes must be the only one over its buffer, so it is
+/// neither `Clone` nor `Sync`, and only a [`Deserializer`] can make one;
+/// reading ahead goes through a copy that never writes.
+///
+/// [`Deserializer`]: crate::de::Deserializer
+/// [`Deserializer::from_mut_str`]: crate::de::Deserializer::from_mut_str
 pub struct Tokenizer<'a> {
-    input: &'a str,
-    chars: str::CharIndices<'a>,
+    ptr: *const u8,
+    len: usize,
+    pos: usize,
+    in_place: bool,
+    marker: PhantomData<&'a str>,
 }
 
+// SAFETY: a tokenizer only reads through its pointer, like a `&'a str`, or,
+// when made from a `&'a mut str`, writes through it with exclusive access.
+unsafe impl Send for Tokenizer<'_> {}
+
+#[allow(clippy::should_implement_trait)]
 impl<'a> Tokenizer<'a> {
     pub fn new(input: &'a str) -> Tokenizer<'a> {
+        Tokenizer::from_raw(input.as_ptr(), input.len(), false)
+    }
+
+    /// Tokenizes a buffer that escaped strings are unescaped into, so that
+    /// every string token borrows from the input. An escape sequence is
+    /// never shorter than the text it stands for, so the decoded string
+    /// always fits where it was; the leftover bytes up to the closing quote
+    /// are blanked with spaces, which keeps the buffer valid UTF-8.
+    pub(crate) fn from_mut_str(input: &'a mut str) -> Tokenizer<'a> {
+        let len = input.len();
+        Tokenizer::from_raw(input.as_mut_ptr(), len, true)
+    }
+
+    fn from_raw(ptr: *const u8, len: usize, in_place: bool) -> Tokenizer<'a> {
         let mut t = Tokenizer {
-            input,
-            chars: input.char_indices(),
+            ptr,
+            len,
+            pos: 0,
+            in_place,
+            marker: PhantomData,
         };
-        t.eatc('\u{feff}');
+        if t.text().starts_with('\u{feff}') {
+            t.pos = '\u{feff}'.len_utf8();
+        }
         t
     }
 
+    /// The input, for as long as `self` is borrowed.
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `ptr` and `len` come from a `&'a str` or `&'a mut str`.
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn text(&self) -> &str {
+        // SAFETY: the input is valid UTF-8, and unescaping in place keeps it
+        // so between tokens.
+        unsafe { str::from_utf8_unchecked(self.bytes()) }
+    }
+
+    /// The input from `start` to `end`, for the whole of `'a`. Both ends
+    /// must be char boundaries, and the bytes must never be written again.
+    fn slice(&self, start: usize, end: usize) -> &'a str {
+        debug_assert!(start <= end && end <= self.len);
+        // SAFETY: in bounds and on char boundaries; the caller promises the
+        // bytes are not written while the slice lives.
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.ptr.add(start), end - start)) }
+    }
+
     pub fn next(&mut self) -> Result<Token<'a>> {
         self.eat_whitespace();
-        match self.next_char() {
-            Some((start, '[' | ']' | '{' | '}')) => self.bracket_token(start),
-            Some((start, ',' | ':')) => self.operator_token(start),
-            Some((start, 't')) => self.bool_token(start, true),
-            Some((start, 'f')) => self.bool_token(start, false),
-            Some((start, 'n')) => self.null_token(start),
-            Some((start, ch @ '0'..='9')) => self.number_token(start),
-            Some((start, '-')) => self.number_token(start),
-            Some((start, '"')) => {
-                let mut s = String::new();
-                return self.string_token(start, &mut s);
+        let start = self.pos;
+        let b = match self.bytes().get(start) {
+            Some(&b) => b,
+            None => return Ok(Token::Eof),
+        };
+        if let Some(punct) = Punct::from_byte(b) {
+            self.pos += 1;
+            return Ok(Token::Punct(punct, (start, self.pos)));
+        }
+        match b {
+            b't' => self.bool_token(start, true),
+            b'f' => self.bool_token(start, false),
+            b'n' => self.null_token(start),
+            b'0'..=b'9' | b'-' => self.number_token(start),
+            b'"' => {
+                self.pos += 1;
+                self.string_token(start)
             }
-            None => Ok(Token::EOF),
-            Some((start, ch)) => Err(Error::NotSupportedChar(ch, start)),
+            _ => Err(Error::NotSupportedChar(self.char_at(start), start)),
         }
     }
 
     pub fn peek(&self) -> Result<Token<'a>> {
-        self.clone().next()
+        self.lookahead().next()
     }
 
-    fn bracket_token(&self, start: usize) -> Result<Token<'a>> {
-        Ok(Token::Bracket(
-            &self.input[start..start + 1],
-            (start, start + 1),
-        ))
+    /// A copy to read ahead with. A lookahead must not write: the strings
+    /// would be decoded again when they are lexed for real.
+    pub(crate) fn lookahead(&self) -> Tokenizer<'a> {
+        Tokenizer {
+            ptr: self.ptr,
+            len: self.len,
+            pos: self.pos,
+            in_place: false,
+            marker: PhantomData,
+        }
     }
 
-    fn operator_token(&self, start: usize) -> Result<Token<'a>> {
-        Ok(Token::Operator(
-            &self.input[start..start + 1],
-            (start, start + 1),
-        ))
+    /// The raw text of the string that comes next if it has escapes, and
+    /// the position after it. Nothing is decoded or written.
+    pub(crate) fn escaped_string(&self) -> Option<(&str, usize)> {
+        let mut t = self.lookahead();
+        t.eat_whitespace();
+        let start = t.pos;
+        if t.bytes().get(start) != Some(&b'"') {
+            return None;
+        }
+        t.skip_string(start).ok()?;
+        let raw = &self.text()[start + 1..t.pos - 1];
+        raw.contains('\\').then_some((raw, t.pos))
+    }
+
+    /// The next byte that is not whitespace, without lexing a token.
+    pub(crate) fn peek_byte(&mut self) -> Option<u8> {
+        self.eat_whitespace();
+        self.bytes().get(self.pos).copied()
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn set_pos(&mut self, pos: usize) {
+        self.pos = pos;
     }
 
     fn bool_token(&mut self, start: usize, val: bool) -> Result<Token<'a>> {
         match val {
-            true => self.parse_ident("rue")?,
-            false => self.parse_ident("alse")?,
+            true => self.parse_ident(b"true")?,
+            false => self.parse_ident(b"false")?,
         }
-        Ok(Token::Bool(val, (start, self.current())))
+        Ok(Token::Bool(val, (start, self.pos)))
     }
 
     fn null_token(&mut self, start: usize) -> Result<Token<'a>> {
-        self.parse_ident("ull")?;
-        Ok(Token::Null((start, self.current())))
+        self.parse_ident(b"null")?;
+        Ok(Token::Null((start, self.pos)))
     }
 
     fn number_token(&mut self, start: usize) -> Result<Token<'a>> {
-        let mut is_scientific = false;
-        loop {
-            match self.peek_char() {
-                Some((_, ch)) => {
-                    if ch == 'e' || ch == 'E' {
-                        is_scientific = true;
-                    }
-                    if is_digit_char(ch) {
-                        self.next_char();
-                    } else {
-                        break;
-                    }
+        let (number, len) = float::scan(self.slice(start, self.len))?;
+        self.pos = start + len;
+        Ok(Token::Number(number, (start, self.pos)))
+    }
+
+    fn parse_ident(&mut self, expected: &[u8]) -> Result<()> {
+        for &ex in expected {
+            match self.bytes().get(self.pos) {
+                Some(&b) if b == ex => self.pos += 1,
+                Some(_) => {
+                    return Err(Error::Wanted {
+                        at: self.pos,
+                        expected: ex as char,
+                        found: self.char_at(self.pos),
+                    })
                 }
-                None => break,
+                None => return Err(Error::EofWhileParsingValue(self.pos)),
             }
         }
-        let s = &self.input[start..self.current()];
-        match s.parse::<i64>() {
-            Ok(num) => Ok(Token::Number(
-                ParseNumber::I64(num),
-                (start, self.current()),
-            )),
-            Err(e) => match s.parse::<f64>() {
-                Ok(num) => Ok(Token::Number(
-                    ParseNumber::F64(num),
-                    (start, self.current()),
-                )),
-                Err(e) => Err(Error::InvalidNumber(s.to_string())),
-            },
+        Ok(())
+    }
+
+    /// Decodes the escape whose backslash is at `start`; `self.pos` is on
+    /// the byte after the backslash.
+    fn parse_escape(&mut self, start: usize) -> Result<char> {
+        let i = self.pos;
+        let b = match self.bytes().get(i) {
+            Some(&b) => b,
+            None => return Err(Error::UnterminatedString(start)),
+        };
+        self.pos += 1;
+        Ok(match b {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => self.hex_escape(start, i, 4)?,
+            b'U' => self.hex_escape(start, i, 8)?,
+            _ => return Err(Error::InvalidEscape(i, self.char_at(i))),
+        })
+    }
+
+    /// Skips one value without building it: strings are not unescaped and
+    /// numbers are checked against the grammar but not converted. Brackets
+    /// must pair up; the separators between members are not checked.
+    pub fn skip_value(&mut self) -> Result<()> {
+        self.skip_rest(Brackets::default())
+    }
+
+    /// Skips the rest of the container whose opening `open` was just read.
+    pub(crate) fn skip_nested(&mut self, open: Punct) -> Result<()> {
+        let mut brackets = Brackets::default();
+        brackets.push(open == Punct::LeftCurly);
+        self.skip_rest(brackets)
+    }
+
+    fn skip_rest(&mut self, mut brackets: Brackets) -> Result<()> {
+        loop {
+            self.skip_token(&mut brackets)?;
+            if brackets.is_empty() {
+                return Ok(());
+            }
         }
     }
 
-    fn parse_ident(&mut self, expected: &str) -> Result<()> {
-        let mut chars = str::char_indices(expected);
-        while let Some((_, ex)) = chars.next() {
-            match self.next_char() {
-                Some((start, ch)) => {
-                    if ch != ex {
-                        return Err(Error::Wanted {
-                            at: start,
-                            expected: ex,
-                            found: ch,
-                        });
-                    }
-                }
-                None => return Err(Error::EofWhileParsingValue(self.current())),
+    /// Skips one token of a value being skipped inside the containers in
+    /// `brackets`.
+    pub(crate) fn skip_token(&mut self, brackets: &mut Brackets) -> Result<()> {
+        self.eat_whitespace();
+        let start = self.pos;
+        let b = match self.bytes().get(start) {
+            Some(&b) => b,
+            None => return Err(Error::EofWhileParsingValue(start)),
+        };
+        match b {
+            b'{' | b'[' => {
+                self.pos += 1;
+                brackets.push(b == b'{');
+            }
+            b'}' | b']' => match brackets.pop() {
+                Some(object) if object == (b == b'}') => self.pos += 1,
+                _ => return Err(Error::Unexpected(start, b as char)),
+            },
+            b',' | b':' if !brackets.is_empty() => self.pos += 1,
+            b',' | b':' => return Err(Error::Unexpected(start, b as char)),
+            b'"' => self.skip_string(start)?,
+            b't' => self.parse_ident(b"true")?,
+            b'f' => self.parse_ident(b"false")?,
+            b'n' => self.parse_ident(b"null")?,
+            b'0'..=b'9' | b'-' => {
+                let (_, len) = float::scan(self.slice(start, self.len))?;
+                self.pos = start + len;
             }
+            _ => return Err(Error::NotSupportedChar(self.char_at(start), start)),
         }
         Ok(())
     }
 
-    fn parse_escape(&mut self, start: usize, scratch: &mut String) -> Result<()> {
-        match self.next_char() {
-            Some((_, '"')) => scratch.push('"'),
-            Some((_, '\\')) => scratch.push('\\'),
-            Some((_, '/')) => scratch.push('/'),
-            Some((_, 'b')) => scratch.push('\u{8}'),
-            Some((_, 'f')) => scratch.push('\u{c}'),
-            Some((_, 'n')) => scratch.push('\n'),
-            Some((_, 'r')) => scratch.push('\r'),
-            Some((_, 't')) => scratch.push('\t'),
-            Some((i, c @ 'u')) | Some((i, c @ 'U')) => {
-                let len = if c == 'u' { 4 } else { 8 };
-                scratch.push(self.hex(start, i, len)?);
+    fn skip_string(&mut self, start: usize) -> Result<()> {
+        self.pos += 1;
+        loop {
+            self.pos = scan::find_string_special(self.bytes(), self.pos);
+            match self.bytes().get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'\\') => self.pos += 2,
+                Some(&b) => return Err(Error::InvalidCharInString(self.pos, b as char)),
+                None => return Err(Error::UnterminatedString(start)),
             }
-            Some((i, ch)) => return Err(Error::InvalidEscape(i, ch)),
-            None => return Err(Error::UnterminatedString(start)),
         }
-        Ok(())
     }
 
-    fn string_token(&mut self, start: usize, scratch: &mut String) -> Result<Token<'a>> {
+    /// Scans a string whose opening quote is at `start`. Unescaped strings
+    /// are borrowed from the input; escaped ones are copied, or decoded in
+    /// place when the tokenizer owns a mutable buffer.
+    fn string_token(&mut self, start: usize) -> Result<Token<'a>> {
+        let mut scratch = String::new();
         let mut escaped = false;
+        let mut run = self.pos;
         loop {
-            match self.next_char() {
-                Some((cur, '\\')) => {
-                    print!("escaped");
-                    scratch.push_str(&self.input[start + 1..cur]);
-                    escaped = true;
-                    self.parse_escape(cur, scratch)?
-                }
-                Some((cur, '"')) => {
+            self.pos = scan::find_string_special(self.bytes(), self.pos);
+            match self.bytes().get(self.pos) {
+                Some(b'"') => {
+                    let raw = self.slice(run, self.pos);
+                    self.pos += 1;
+                    let span = (start, self.pos);
                     return if escaped {
-                        Ok(Token::String(
-                            MaybeString::Escaped(scratch.to_string()),
-                            (start, self.current()),
-                        ))
+                        scratch.push_str(raw);
+                        Ok(Token::String(MaybeString::Escaped(scratch), span))
                     } else {
-                        Ok(Token::String(
-                            MaybeString::NotEscaped(&self.input[start + 1..self.current() - 1]),
-                            (start, self.current()),
-                        ))
-                    }
+                        Ok(Token::String(MaybeString::NotEscaped(raw), span))
+                    };
                 }
-                Some((_, c)) => {
-                    if escaped {
-                        scratch.push(c);
-                    }
+                Some(&b) if b < 0x20 => {
+                    return Err(Error::InvalidCharInString(self.pos, b as char));
                 }
-                None => {
-                    return Err(Error::UnterminatedString(self.current()));
+                Some(_) if self.in_place => return self.unescape_in_place(start),
+                Some(_) => {
+                    scratch.push_str(&self.text()[run..self.pos]);
+                    escaped = true;
+                    let cur = self.pos;
+                    self.pos += 1;
+                    scratch.push(self.parse_escape(cur)?);
+                    run = self.pos;
                 }
+                None => return Err(Error::UnterminatedString(self.pos)),
             }
         }
     }
 
-    fn hex(&mut self, start: usize, i: usize, len: usize) -> Result<char> {
-        let mut buf = String::with_capacity(len);
-        for _ in 0..len {
-            match self.next_char() {
-                Some((_, ch)) if ch as u32 <= 0x7F && ch.is_digit(16) => buf.push(ch),
-                Some((i, ch)) => return Err(Error::InvalidHexEscape(i, ch)),
-                None => return Err(Error::UnterminatedString(start)),
+    /// Decodes the rest of the string from the backslash at `self.pos` over
+    /// its own source text, and borrows the result.
+    fn unescape_in_place(&mut self, start: usize) -> Result<Token<'a>> {
+        let (mut write, mut read) = (self.pos, self.pos);
+        let result = self.unescape_runs(start, &mut write, &mut read);
+        // Whatever the outcome, the bytes between the decoded text and the
+        // undecoded rest are stale and may split a char.
+        let stale = write - start..read - start;
+        // SAFETY: see `buf_mut`; the token below is made after this write.
+        let buf = unsafe { self.buf_mut(start) };
+        buf[stale].fill(b' ');
+        result?;
+        let text = self.slice(start + 1, write);
+        self.pos += 1;
+        Ok(Token::String(
+            MaybeString::NotEscaped(text),
+            (start, self.pos),
+        ))
+    }
+
+    /// Decodes escapes and moves the plain runs between them down to
+    /// `write`, until the closing quote or an error. Bytes from `read` on
+    /// are still as they were.
+    fn unescape_runs(&mut self, start: usize, write: &mut usize, read: &mut usize) -> Result<()> {
+        loop {
+            let cur = self.pos;
+            *read = cur;
+            self.pos += 1;
+            let ch = self.parse_escape(cur)?;
+            let run = self.pos;
+            let end = scan::find_string_special(self.bytes(), run);
+            self.pos = end;
+            // SAFETY: see `buf_mut`.
+            let buf = unsafe { self.buf_mut(start) };
+            // Neither write overtakes `run`: an escape is at least as long
+            // as the UTF-8 it decodes to.
+            *write += ch.encode_utf8(&mut buf[*write - start..]).len();
+            buf.copy_within(run - start..end - start, *write - start);
+            *write += end - run;
+            *read = end;
+            match self.bytes().get(self.pos) {
+                Some(b'"') => return Ok(()),
+                Some(b'\\') => {}
+                Some(&b) => return Err(Error::InvalidCharInString(self.pos, b as char)),
+                None => return Err(Error::UnterminatedString(self.pos)),
             }
         }
-        let val = u32::from_str_radix(&buf, 16).unwrap();
-        match char::from_u32(val) {
-            Some(ch) => Ok(ch),
-            None => Err(Error::InvalidEscapeValue(i, val)),
-        }
     }
 
-    fn current(&self) -> usize {
-        self.chars
-            .clone()
-            .next()
-            .map(|i| i.0)
-            .unwrap_or_else(|| self.input.len())
+    /// The input from the string starting at `start` to the end, writable.
+    ///
+    /// # Safety
+    ///
+    /// The tokenizer must have been made by `from_mut_str`, and no other view
+    /// of these bytes may be used while the result lives. Tokens only borrow
+    /// bytes before the current string, so only the tokenizer's own
+    /// temporary views can overlap.
+    unsafe fn buf_mut(&mut self, start: usize) -> &mut [u8] {
+        debug_assert!(self.in_place);
+        slice::from_raw_parts_mut(self.ptr.add(start) as *mut u8, self.len - start)
     }
 
-    pub fn eat_whitespace(&mut self) {
-        loop {
-            match self.peek_char() {
-                Some((_, ch)) => {
-                    if is_whitespace_char(ch) {
-                        self.next_char();
-                    } else {
-                        break;
-                    }
+    fn hex(&mut self, start: usize, len: usize) -> Result<u32> {
+        let mut val: u32 = 0;
+        for _ in 0..len {
+            match self.bytes().get(self.pos) {
+                Some(&b) if b.is_ascii_hexdigit() => {
+                    val = val * 16 + (b as char).to_digit(16).unwrap_or_default();
+                    self.pos += 1;
                 }
-                None => break,
+                Some(_) => return Err(Error::InvalidHexEscape(self.pos, self.char_at(self.pos))),
+                None => return Err(Error::UnterminatedString(start)),
             }
         }
+        Ok(val)
     }
 
-    fn eatc(&mut self, ch: char) -> bool {
-        match self.peek_char() {
-            Some((_, c)) if c == ch => {
-                self.next_char();
-                true
+    /// A `\u` or `\U` escape of `len` hex digits; a `\u` high surrogate
+    /// combines with a `\u` low surrogate right after it.
+    fn hex_escape(&mut self, start: usize, i: usize, len: usize) -> Result<char> {
+        let mut val = self.hex(start, len)?;
+        if len == 4
+            && (0xD800..0xDC00).contains(&val)
+            && self.bytes()[self.pos..].starts_with(b"\\u")
+        {
+            let high = self.pos;
+            self.pos += 2;
+            match self.hex(start, 4)? {
+                low @ 0xDC00..=0xDFFF => val = 0x10000 + ((val - 0xD800) << 10) + (low - 0xDC00),
+                _ => self.pos = high,
             }
-            _ => false,
+        }
+        match char::from_u32(val) {
+            Some(ch) => Ok(ch),
+            None => Err(Error::InvalidEscapeValue(i, val)),
         }
     }
 
-    /// Take one char whithout consuming it
-    fn peek_char(&self) -> Option<(usize, char)> {
-        self.chars.clone().next()
+    /// The char starting at byte `pos`, for error reports.
+    fn char_at(&self, pos: usize) -> char {
+        self.text()[pos..].chars().next().unwrap_or_default()
+    }
+
+    pub fn eat_whitespace(&mut self) {
+        self.pos = scan::skip_whitespace(self.bytes(), self.pos);
     }
 
     /// Take one char
     pub fn next_char(&mut self) -> Option<(usize, char)> {
-        self.chars.next()
+        let start = self.pos;
+        let ch = self.text()[start..].chars().next()?;
+        self.pos += ch.len_utf8();
+        Some((start, ch))
     }
 
-    pub fn expect(&mut self, s: String) -> Result<()> {
-        match self.next()? {
-            Token::Operator(op, _) => {
-                if op == s {
-                    return Ok(());
-                }
+    /// Consumes the punctuation `punct`. Only a byte compare, since no other
+    /// token needs to be lexed to tell it apart.
+    pub fn expect(&mut self, punct: Punct) -> Result<()> {
+        self.eat_whitespace();
+        match self.bytes().get(self.pos) {
+            Some(&b) if b == punct as u8 => {
+                self.pos += 1;
+                Ok(())
             }
-            Token::Bracket(b, _) => {
-                if b == s {
-                    return Ok(());
-                }
+            Some(_) => Err(Error::Wanted {
+                at: self.pos,
+                expected: punct as u8 as char,
+                found: self.char_at(self.pos),
+            }),
+            None => Err(Error::EofWhileParsingValue(self.pos)),
+        }
+    }
+
+    /// Starts the next array element: consumes the `]` that ends the array
+    /// and returns false, or consumes the `,` before any element but the
+    /// first and returns true.
+    pub(crate) fn element_start(&mut self, first: bool) -> Result<bool> {
+        self.eat_whitespace();
+        match self.bytes().get(self.pos) {
+            Some(b']') => {
+                self.pos += 1;
+                Ok(false)
             }
-            _ => return Err(Error::OpNotExist(s.to_string())),
+            _ if first => Ok(true),
+            _ => self.expect(Punct::Comma).map(|_| true),
         }
-        Err(Error::OpNotExist(s.to_string()))
     }
-}
 
-fn is_digit_char(ch: char) -> bool {
-    match ch {
-        _c @ '0'..='9' => true,
-        '.' | 'e' | 'E' | '-' | '+' => true,
-        _ => false,
+    /// Lexes the next value if it is a number, without building a token.
+    pub(crate) fn number(&mut self) -> Result<Option<ParseNumber<'a>>> {
+        self.eat_whitespace();
+        let start = self.pos;
+        match self.bytes().get(start) {
+            Some(b'0'..=b'9' | b'-') => {
+                let (number, len) = float::scan(self.slice(start, self.len))?;
+                self.pos = start + len;
+                Ok(Some(number))
+            }
+            _ => Ok(None),
+        }
     }
-}
 
-fn is_whitespace_char(ch: char) -> bool {
-    match ch {
-        ' ' | '\t' | '\r' | '\n' => true,
-        _ => false,
+    /// The error for finding `token` where `punct` was expected.
+    pub(crate) fn unexpected(&self, token: &Token, punct: Punct) -> Error {
+        match token.span() {
+            Some((at, _)) => Error::Wanted {
+                at,
+                expected: punct as u8 as char,
+                found: self.char_at(at),
+            },
+            None => Error::EofWhileParsingValue(self.len),
+        }
     }
 }
 
@@ -285,9 +555,8 @@ fn test_tokenizer() {
         match tokenizer.next() {
             Ok(token) => {
                 println!("Token {:?}", token);
-                match token {
-                    Token::EOF => break,
-                    _ => {}
+                if let Token::Eof = token {
+                    break;
                 }
             }
             Err(e) => {
@@ -297,3 +566,47 @@ fn test_tokenizer() {
         }
     }
 }
+
+#[test]
+fn test_tokenizer_spans() {
+    let s = "\u{feff}{\"k\\n\u{e9}\": [-1.5, true, null]}";
+    let mut tokenizer = Tokenizer::new(s);
+    let mut tokens = Vec::new();
+    loop {
+        match tokenizer.next().unwrap() {
+            Token::Eof => break,
+            Token::Punct(p, span) => tokens.push(format!("{:?}{:?}", p, span)),
+            Token::String(MaybeString::Escaped(s), span) => tokens.push(format!("{}{:?}", s, span)),
+            token => tokens.push(format!("{:?}", token)),
+        }
+    }
+    assert_eq!(tokens[0], "LeftCurly(3, 4)");
+    assert_eq!(tokens[1], "k\n\u{e9}(4, 11)");
+    assert_eq!(tokens[2], "Colon(11, 12)");
+    assert_eq!(tokens[3], "LeftBracket(13, 14)");
+    assert_eq!(tokens[6], "Bool(true, (20, 24))");
+    assert_eq!(tokens[8], "Null((26, 30))");
+    assert_eq!(tokens[9], "RightBracket(30, 31)");
+
+    assert_eq!(
+        Tokenizer::new("nul").next().unwrap_err(),
+        Error::EofWhileParsingValue(3)
+    );
+    assert_eq!(
+        Tokenizer::new("  \u{e9}").next().unwrap_err(),
+        Error::NotSupportedChar('\u{e9}', 2)
+    );
+}
+
+#[test]
+fn test_control_char_in_string() {
+    assert_eq!(
+        Tokenizer::new("\"a\tb\"").next().unwrap_err(),
+        Error::InvalidCharInString(2, '\t')
+    );
+    let long = format!("\"{}\\n{}\"", "x".repeat(40), "y".repeat(40));
+    match Tokenizer::new(&long).next().unwrap() {
+        Token::String(MaybeString::Escaped(s), _) => assert_eq!(s.len(), 81),
+        token => panic!("{:?}", token),
+    }
+}
@@ -0,0 +1,980 @@
+//! JSON Schema generation from Rust types.
+//!
+//! [`schema`] drives a type's `Deserialize` impl through a deserializer that
+//! hands it sample values and records what it asks for: field names from
+//! `deserialize_struct`, variant names from `deserialize_enum`, options,
+//! sequences, tuples and maps. No derive is needed, so any type can be
+//! traced, including ones from other crates.
+//!
+//! Structs and enums are told apart by the type of the visitor their
+//! `Deserialize` impl passes, not just by name, so `Wrapper<u8>` and
+//! `Wrapper<String>` each get a definition, named `Wrapper` and `Wrapper2`
+//! in the order they are found, and `Wrapper<Wrapper<u8>>` is not mistaken
+//! for recursion.
+//!
+//! Every variant of every enum is traced, which can take several passes over
+//! the type. Recursion is handled when it goes through an `Option`, a
+//! sequence, a map or another variant of the enum: the inner occurrence
+//! becomes a `$ref`, and later passes stop short of it. Types that need
+//! `deserialize_any`, such as untagged or internally tagged enums, cannot be
+//! traced, and neither can types that reject the sample values (`1`, `0.0`,
+//! `""` and so on). Defaults are invisible to a deserializer, so fields with
+//! `#[serde(default)]` are still listed as `required`; only `Option` fields
+//! are not.
+
+use crate::error::Error;
+use crate::intern;
+use crate::tokenizer::Result;
+use crate::value::{Map, Number, Value};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// The error a pass ends with when it runs into a recursion it can cut off
+/// next time.
+const RECURSION: &str = "$json_rs::private::Recursion";
+
+/// The schema of `T`, with named structs and enums under `$defs`.
+pub fn schema<T>() -> Result<Value>
+where
+    T: Deserialize<'static>,
+{
+    let mut ctx = Context::default();
+    let mut root = Value::Null;
+    loop {
+        ctx.progress = false;
+        let tracer = Tracer {
+            ctx: &mut ctx,
+            slot: &mut root,
+            path: String::new(),
+            field: false,
+        };
+        match T::deserialize(tracer) {
+            Ok(_) if ctx.progress && ctx.unexplored() => {}
+            Ok(_) => break,
+            Err(Error::Message(msg)) if msg == RECURSION => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let mut document = Map::new();
+    document.insert(
+        "$schema".to_string(),
+        Value::String("https://json-schema.org/draft/2020-12/schema".to_string()),
+    );
+    if let Value::Object(root) = root {
+        document.extend(root);
+    }
+    if !ctx.defs.is_empty() {
+        document.insert("$defs".to_string(), Value::Object(ctx.defs));
+    }
+    Ok(Value::Object(document))
+}
+
+/// The text of the schema of `T`.
+pub fn schema_string<T>() -> Result<String>
+where
+    T: Deserialize<'static>,
+{
+    crate::ser::to_string(&schema::<T>()?)
+}
+
+#[derive(Default)]
+struct Context {
+    defs: Map,
+    /// The name under `$defs` of each struct and enum, by visitor type.
+    keys: HashMap<&'static str, String>,
+    /// The schema of each variant of each enum, once it has been traced.
+    enums: HashMap<&'static str, Vec<Option<Value>>>,
+    in_progress: Vec<Container>,
+    /// The options, sequences and maps being traced, innermost last.
+    escapes: Vec<String>,
+    /// Options, sequences and maps found to recurse, which later passes
+    /// leave empty, and the schema of the contents of each.
+    empty_at: HashSet<String>,
+    contents: HashMap<String, Value>,
+    /// Whether the field just traced is an `Option`.
+    optional: bool,
+    /// Whether this pass found anything new.
+    progress: bool,
+}
+
+/// A struct or enum being traced.
+struct Container {
+    /// The type name of its visitor, which tells generic instances apart.
+    id: &'static str,
+    variant: Option<usize>,
+    /// How many escapes were open when it started.
+    escapes: usize,
+}
+
+impl Context {
+    /// The name under `$defs` of the type `name` whose visitor is `id`: the
+    /// name itself, or the name with a number if another type has it.
+    fn def_key(&mut self, name: &str, id: &'static str) -> String {
+        if let Some(key) = self.keys.get(id) {
+            return key.clone();
+        }
+        let taken = |key: &str| self.keys.values().any(|k| k == key);
+        let mut key = name.to_string();
+        let mut n = 1;
+        while taken(&key) {
+            n += 1;
+            key = format!("{}{}", name, n);
+        }
+        self.keys.insert(id, key.clone());
+        key
+    }
+
+    /// Cuts off the recursion into the type with visitor `id` at the
+    /// innermost escape since its outermost occurrence.
+    fn recursion(&mut self, id: &'static str, name: &str) -> Error {
+        let depth = self
+            .in_progress
+            .iter()
+            .find(|container| container.id == id)
+            .map_or(0, |container| container.escapes);
+        match self.escapes[depth..].last() {
+            Some(escape) => {
+                self.empty_at.insert(escape.clone());
+                self.progress = true;
+                Error::Message(RECURSION.to_string())
+            }
+            None => Error::Message(format!(
+                "cannot trace {}: it contains itself with no Option, sequence or map in between",
+                name
+            )),
+        }
+    }
+
+    fn unexplored(&self) -> bool {
+        self.enums.values().flatten().any(Option::is_none)
+    }
+
+    /// Runs `trace` on a tracer for the contents at `path`, or on `None` if
+    /// an earlier pass found they recurse, and returns its result with the
+    /// schema of the contents.
+    fn escape<T>(
+        &mut self,
+        path: &str,
+        segment: &str,
+        trace: impl FnOnce(Option<Tracer<'_>>) -> T,
+    ) -> (T, Value) {
+        if self.empty_at.contains(path) {
+            let result = trace(None);
+            return (result, self.contents[path].clone());
+        }
+        self.escapes.push(path.to_string());
+        let mut contents = Value::Null;
+        let result = trace(Some(Tracer {
+            ctx: &mut *self,
+            slot: &mut contents,
+            path: format!("{}/{}", path, segment),
+            field: false,
+        }));
+        self.escapes.pop();
+        self.contents.insert(path.to_string(), contents.clone());
+        (result, contents)
+    }
+}
+
+/// Deserializes one value with sample data, and writes its schema to `slot`.
+struct Tracer<'c> {
+    ctx: &'c mut Context,
+    slot: &'c mut Value,
+    /// Where the value is, for telling apart the escapes of one pass.
+    path: String,
+    /// Whether the value is a struct field.
+    field: bool,
+}
+
+macro_rules! trace_integer {
+    ($method:ident, $visit:ident, $ty:ty, $min:expr, $max:expr) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'static>,
+        {
+            *self.slot = integer($min, $max);
+            visitor.$visit(1 as $ty)
+        }
+    };
+}
+
+impl<'c> Tracer<'c> {
+    fn fields<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let mut access = Fields {
+            ctx: self.ctx,
+            path: &self.path,
+            fields,
+            index: 0,
+            properties: Map::new(),
+            required: Vec::new(),
+        };
+        let result = visitor.visit_map(&mut access);
+        *self.slot = keywords([
+            ("type", Value::String("object".to_string())),
+            ("properties", Value::Object(access.properties)),
+            ("required", Value::Array(access.required)),
+        ]);
+        result
+    }
+}
+
+impl<'c> de::Deserializer<'static> for Tracer<'c> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        Err(Error::Message(format!(
+            "cannot trace the value at {:?}: it needs deserialize_any",
+            self.path
+        )))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = simple("boolean");
+        visitor.visit_bool(false)
+    }
+
+    trace_integer!(
+        deserialize_i8,
+        visit_i8,
+        i8,
+        Some(i8::MIN as i64),
+        Some(i8::MAX as u64)
+    );
+    trace_integer!(
+        deserialize_i16,
+        visit_i16,
+        i16,
+        Some(i16::MIN as i64),
+        Some(i16::MAX as u64)
+    );
+    trace_integer!(
+        deserialize_i32,
+        visit_i32,
+        i32,
+        Some(i32::MIN as i64),
+        Some(i32::MAX as u64)
+    );
+    trace_integer!(deserialize_i64, visit_i64, i64, None, None);
+    trace_integer!(deserialize_u8, visit_u8, u8, Some(0), Some(u8::MAX as u64));
+    trace_integer!(
+        deserialize_u16,
+        visit_u16,
+        u16,
+        Some(0),
+        Some(u16::MAX as u64)
+    );
+    trace_integer!(
+        deserialize_u32,
+        visit_u32,
+        u32,
+        Some(0),
+        Some(u32::MAX as u64)
+    );
+    trace_integer!(deserialize_u64, visit_u64, u64, Some(0), None);
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = simple("number");
+        visitor.visit_f32(0.0)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = simple("number");
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = keywords([
+            ("type", Value::String("string".to_string())),
+            ("minLength", Value::Number(Number::U64(1))),
+            ("maxLength", Value::Number(Number::U64(1))),
+        ]);
+        visitor.visit_char('a')
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = simple("string");
+        visitor.visit_borrowed_str("")
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = keywords([
+            ("type", Value::String("array".to_string())),
+            ("items", integer(Some(0), Some(u8::MAX as u64))),
+        ]);
+        visitor.visit_borrowed_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let (result, contents) = self.ctx.escape(&self.path, "?", |tracer| match tracer {
+            Some(tracer) => visitor.visit_some(tracer),
+            None => visitor.visit_none(),
+        });
+        *self.slot = nullable(contents);
+        // Set last, as fields inside reset it.
+        if self.field {
+            self.ctx.optional = true;
+        }
+        result
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = simple("null");
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        if name == intern::TOKEN {
+            return self.deserialize_str(visitor);
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let (result, items) = self.ctx.escape(&self.path, "*", |tracer| {
+            visitor.visit_seq(Elements {
+                tracer,
+                tuple: None,
+            })
+        });
+        *self.slot = keywords([
+            ("type", Value::String("array".to_string())),
+            ("items", items),
+        ]);
+        result
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let mut access = Elements {
+            tracer: None,
+            tuple: Some(Tuple {
+                ctx: self.ctx,
+                path: &self.path,
+                len,
+                items: Vec::with_capacity(len),
+            }),
+        };
+        let result = visitor.visit_seq(&mut access);
+        let items = access.tuple.map_or_else(Vec::new, |tuple| tuple.items);
+        *self.slot = keywords([
+            ("type", Value::String("array".to_string())),
+            ("prefixItems", Value::Array(items)),
+            ("minItems", Value::Number(Number::U64(len as u64))),
+            ("maxItems", Value::Number(Number::U64(len as u64))),
+        ]);
+        result
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let (result, values) = self.ctx.escape(&self.path, "*", |tracer| {
+            visitor.visit_map(Entries { tracer })
+        });
+        *self.slot = keywords([
+            ("type", Value::String("object".to_string())),
+            ("additionalProperties", values),
+        ]);
+        result
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let id = std::any::type_name::<V>();
+        let key = self.ctx.def_key(name, id);
+        *self.slot = reference(&key);
+        if self.ctx.in_progress.iter().any(|c| c.id == id) {
+            return Err(self.ctx.recursion(id, name));
+        }
+        let escapes = self.ctx.escapes.len();
+        self.ctx.in_progress.push(Container {
+            id,
+            variant: None,
+            escapes,
+        });
+        let mut def = Value::Null;
+        let tracer = Tracer {
+            ctx: &mut *self.ctx,
+            slot: &mut def,
+            path: self.path,
+            field: false,
+        };
+        let result = tracer.fields(fields, visitor);
+        self.ctx.in_progress.pop();
+        if result.is_ok() {
+            self.ctx.defs.insert(key, def);
+        }
+        result
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        let id = std::any::type_name::<V>();
+        let key = self.ctx.def_key(name, id);
+        *self.slot = reference(&key);
+        let traced: Vec<bool> = self
+            .ctx
+            .enums
+            .entry(id)
+            .or_insert_with(|| vec![None; variants.len()])
+            .iter()
+            .map(Option::is_some)
+            .collect();
+        let active: Vec<usize> = self
+            .ctx
+            .in_progress
+            .iter()
+            .filter(|c| c.id == id)
+            .filter_map(|c| c.variant)
+            .collect();
+        let all = 0..variants.len();
+        // Explore a new variant where possible; inside itself, prefer one
+        // known to end, then one not already open.
+        let index = if active.is_empty() {
+            all.clone().find(|&i| !traced[i]).or(all.clone().next())
+        } else {
+            all.clone()
+                .find(|&i| traced[i])
+                .or_else(|| all.clone().find(|i| !active.contains(i)))
+        };
+        let index = match index {
+            Some(index) => index,
+            None if variants.is_empty() => {
+                return Err(Error::Message(format!("cannot trace empty enum {}", name)))
+            }
+            None => return Err(self.ctx.recursion(id, name)),
+        };
+        let escapes = self.ctx.escapes.len();
+        self.ctx.in_progress.push(Container {
+            id,
+            variant: Some(index),
+            escapes,
+        });
+        let mut schema = Value::Null;
+        let result = visitor.visit_enum(Variant {
+            tracer: Tracer {
+                ctx: &mut *self.ctx,
+                slot: &mut schema,
+                path: format!("{}/{}", self.path, variants[index]),
+                field: false,
+            },
+            variant: variants[index],
+        });
+        self.ctx.in_progress.pop();
+        if result.is_ok() {
+            let schemas = self.ctx.enums.get_mut(id).expect("registered above");
+            if schemas[index].is_none() {
+                self.ctx.progress = true;
+            }
+            schemas[index] = Some(schema);
+            let def = enum_def(schemas);
+            self.ctx.defs.insert(key, def);
+        }
+        result
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        *self.slot = Value::Bool(true);
+        visitor.visit_unit()
+    }
+}
+
+/// The members of a struct, each traced once.
+struct Fields<'a> {
+    ctx: &'a mut Context,
+    path: &'a str,
+    fields: &'static [&'static str],
+    index: usize,
+    properties: Map,
+    required: Vec<Value>,
+}
+
+impl<'a> de::MapAccess<'static> for Fields<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'static>,
+    {
+        match self.fields.get(self.index) {
+            Some(name) => seed.deserialize(name.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'static>,
+    {
+        let name = self.fields[self.index];
+        self.index += 1;
+        self.ctx.optional = false;
+        let mut schema = Value::Null;
+        let value = seed.deserialize(Tracer {
+            ctx: &mut *self.ctx,
+            slot: &mut schema,
+            path: format!("{}/{}", self.path, name),
+            field: true,
+        })?;
+        if !std::mem::take(&mut self.ctx.optional) {
+            self.required.push(Value::String(name.to_string()));
+        }
+        self.properties.insert(name.to_string(), schema);
+        Ok(value)
+    }
+}
+
+/// The elements of a sequence, one sample if `tracer` is set, or of a
+/// tuple.
+struct Elements<'a> {
+    tracer: Option<Tracer<'a>>,
+    tuple: Option<Tuple<'a>>,
+}
+
+struct Tuple<'a> {
+    ctx: &'a mut Context,
+    path: &'a str,
+    len: usize,
+    items: Vec<Value>,
+}
+
+impl<'a> de::SeqAccess<'static> for Elements<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'static>,
+    {
+        if let Some(tracer) = self.tracer.take() {
+            return seed.deserialize(tracer).map(Some);
+        }
+        let tuple = match &mut self.tuple {
+            Some(tuple) if tuple.items.len() < tuple.len => tuple,
+            _ => return Ok(None),
+        };
+        let mut schema = Value::Null;
+        let value = seed.deserialize(Tracer {
+            ctx: &mut *tuple.ctx,
+            slot: &mut schema,
+            path: format!("{}/{}", tuple.path, tuple.items.len()),
+            field: false,
+        });
+        tuple.items.push(schema);
+        value.map(Some)
+    }
+}
+
+/// The entries of a map: one sample if `tracer` is set.
+struct Entries<'a> {
+    tracer: Option<Tracer<'a>>,
+}
+
+impl<'a> de::MapAccess<'static> for Entries<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'static>,
+    {
+        let tracer = match &mut self.tracer {
+            Some(tracer) => tracer,
+            None => return Ok(None),
+        };
+        // JSON keys are strings whatever the key type.
+        let mut schema = Value::Null;
+        seed.deserialize(Tracer {
+            ctx: &mut *tracer.ctx,
+            slot: &mut schema,
+            path: format!("{}/key", tracer.path),
+            field: false,
+        })
+        .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'static>,
+    {
+        match self.tracer.take() {
+            Some(tracer) => seed.deserialize(tracer),
+            None => Err(Error::Message("map value without a key".to_string())),
+        }
+    }
+}
+
+/// The variant of an enum being traced; its schema goes to the tracer's
+/// slot.
+struct Variant<'a> {
+    tracer: Tracer<'a>,
+    variant: &'static str,
+}
+
+impl<'a> de::EnumAccess<'static> for Variant<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self)>
+    where
+        T: DeserializeSeed<'static>,
+    {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'a> de::VariantAccess<'static> for Variant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        *self.tracer.slot = keywords([("const", Value::String(self.variant.to_string()))]);
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'static>,
+    {
+        self.payload(|tracer| seed.deserialize(tracer))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.payload(|tracer| de::Deserializer::deserialize_tuple(tracer, len, visitor))
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'static>,
+    {
+        self.payload(|tracer| tracer.fields(fields, visitor))
+    }
+}
+
+impl<'a> Variant<'a> {
+    /// Traces the payload with `trace`, and records the variant as an
+    /// object with the payload under the variant's name.
+    fn payload<T>(self, trace: impl FnOnce(Tracer<'_>) -> Result<T>) -> Result<T> {
+        let Variant { tracer, variant } = self;
+        let mut payload = Value::Null;
+        let result = trace(Tracer {
+            ctx: tracer.ctx,
+            slot: &mut payload,
+            path: tracer.path,
+            field: false,
+        });
+        let mut properties = Map::new();
+        properties.insert(variant.to_string(), payload);
+        *tracer.slot = keywords([
+            ("type", Value::String("object".to_string())),
+            ("properties", Value::Object(properties)),
+            (
+                "required",
+                Value::Array(vec![Value::String(variant.to_string())]),
+            ),
+            ("additionalProperties", Value::Bool(false)),
+        ]);
+        result
+    }
+}
+
+fn keywords<const N: usize>(members: [(&str, Value); N]) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
+
+fn simple(name: &str) -> Value {
+    keywords([("type", Value::String(name.to_string()))])
+}
+
+fn integer(min: Option<i64>, max: Option<u64>) -> Value {
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), Value::String("integer".to_string()));
+    if let Some(min) = min {
+        let min = match u64::try_from(min) {
+            Ok(min) => Number::U64(min),
+            Err(_) => Number::I64(min),
+        };
+        schema.insert("minimum".to_string(), Value::Number(min));
+    }
+    if let Some(max) = max {
+        schema.insert("maximum".to_string(), Value::Number(Number::U64(max)));
+    }
+    Value::Object(schema)
+}
+
+fn reference(name: &str) -> Value {
+    keywords([("$ref", Value::String(format!("#/$defs/{}", name)))])
+}
+
+/// `schema` or `null`: a `type` gains `"null"`, anything else is wrapped in
+/// `anyOf`.
+fn nullable(schema: Value) -> Value {
+    match schema {
+        Value::Object(mut map) if map.get("type").is_some_and(|t| t.as_str().is_some()) => {
+            let name = map.remove("type").unwrap_or_default();
+            let types = vec![name, Value::String("null".to_string())];
+            map.insert("type".to_string(), Value::Array(types));
+            Value::Object(map)
+        }
+        schema => keywords([("anyOf", Value::Array(vec![schema, simple("null")]))]),
+    }
+}
+
+/// The schema of an enum from those of its variants traced so far: unit
+/// variants are strings, the others objects keyed by the variant name.
+fn enum_def(variants: &[Option<Value>]) -> Value {
+    let mut units = Vec::new();
+    let mut others = Vec::new();
+    for schema in variants.iter().flatten() {
+        match schema.as_object().and_then(|map| map.get("const")) {
+            Some(name) => units.push(name.clone()),
+            None => others.push(schema.clone()),
+        }
+    }
+    if units.is_empty() {
+        return keywords([("oneOf", Value::Array(others))]);
+    }
+    let units = keywords([
+        ("type", Value::String("string".to_string())),
+        ("enum", Value::Array(units)),
+    ]);
+    if others.is_empty() {
+        return units;
+    }
+    others.insert(0, units);
+    keywords([("oneOf", Value::Array(others))])
+}
+
+#[test]
+fn test_trace() {
+    use crate::schema::Validator;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct Tree {
+        name: String,
+        size: Option<u8>,
+        children: Vec<Tree>,
+        shape: Shape,
+        tags: BTreeMap<String, (i32, f64)>,
+        key: intern::Key,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Pair(Box<Shape>, Box<Shape>),
+        Unknown,
+    }
+
+    let document = schema::<Tree>().unwrap();
+    assert_eq!(
+        crate::ser::to_string(document.pointer("/$defs/Tree/properties/size").unwrap()).unwrap(),
+        r#"{"maximum":255,"minimum":0,"type":["integer","null"]}"#
+    );
+    assert_eq!(
+        crate::ser::to_string(document.pointer("/$defs/Tree/required").unwrap()).unwrap(),
+        r#"["name","children","shape","tags","key"]"#
+    );
+    assert_eq!(
+        crate::ser::to_string(document.pointer("/$defs/Shape/oneOf/0").unwrap()).unwrap(),
+        r#"{"enum":["Empty","Unknown"],"type":"string"}"#
+    );
+    assert_eq!(
+        document
+            .pointer("/$defs/Shape/oneOf")
+            .and_then(Value::as_array)
+            .map(Vec::len),
+        Some(4)
+    );
+
+    let validator = Validator::new(&document).unwrap();
+    let tree = Tree {
+        name: "root".to_string(),
+        size: None,
+        children: vec![Tree {
+            name: "leaf".to_string(),
+            size: Some(3),
+            children: Vec::new(),
+            shape: Shape::Pair(
+                Box::new(Shape::Circle(1.5)),
+                Box::new(Shape::Rect { w: 1, h: 2 }),
+            ),
+            tags: BTreeMap::from([("t".to_string(), (-1, 0.5))]),
+            key: "k".into(),
+        }],
+        shape: Shape::Empty,
+        tags: BTreeMap::new(),
+        key: "k".into(),
+    };
+    let instance: Value = crate::de::from_str(&crate::ser::to_string(&tree).unwrap()).unwrap();
+    assert_eq!(validator.validate(&instance), []);
+
+    let bad = r#"{"name": "x", "size": 256, "children": [], "shape": {"Rect": {"w": 1}}, "tags": {"a": [1]}, "key": "k"}"#;
+    let errors = validator.validate(&crate::de::from_str(bad).unwrap());
+    let paths: Vec<&str> = errors.iter().map(|e| e.instance_path.as_str()).collect();
+    assert_eq!(paths, ["/shape", "/size", "/tags/a"]);
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Endless {
+        next: Box<Endless>,
+    }
+    assert!(schema::<Endless>().is_err());
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum Untagged {
+        A(u8),
+    }
+    assert!(schema::<Untagged>().is_err());
+
+    // Instances of one generic type get a definition each.
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper<T> {
+        inner: T,
+    }
+    #[derive(Serialize, Deserialize)]
+    struct Outer {
+        a: Wrapper<u8>,
+        b: Wrapper<String>,
+        c: Wrapper<Wrapper<u8>>,
+    }
+    let document = schema::<Outer>().unwrap();
+    let def = |name: &str| {
+        let at = format!("/$defs/{}/properties/inner", name);
+        crate::ser::to_string(document.pointer(&at).unwrap()).unwrap()
+    };
+    assert_eq!(
+        def("Wrapper"),
+        r#"{"maximum":255,"minimum":0,"type":"integer"}"#
+    );
+    assert_eq!(def("Wrapper2"), r#"{"type":"string"}"#);
+    assert_eq!(def("Wrapper3"), r##"{"$ref":"#/$defs/Wrapper"}"##);
+    let outer = Outer {
+        a: Wrapper { inner: 1 },
+        b: Wrapper {
+            inner: "b".to_string(),
+        },
+        c: Wrapper {
+            inner: Wrapper { inner: 2 },
+        },
+    };
+    let instance: Value = crate::de::from_str(&crate::ser::to_string(&outer).unwrap()).unwrap();
+    let validator = Validator::new(&document).unwrap();
+    assert_eq!(validator.validate(&instance), []);
+    let swapped = r#"{"a": {"inner": "a"}, "b": {"inner": 1}, "c": {"inner": {"inner": 2}}}"#;
+    assert_eq!(
+        validator
+            .validate(&crate::de::from_str(swapped).unwrap())
+            .len(),
+        2
+    );
+}
@@ -0,0 +1,337 @@
+//! A dynamically typed JSON document, for code that works on documents
+//! whose shape is not known at compile time.
+
+use crate::pointer;
+use serde::{de, ser, Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub type Map = BTreeMap<String, Value>;
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map),
+}
+
+/// A number as it was read: integers keep their exact value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+impl Number {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::U64(n) => n as f64,
+            Number::I64(n) => n as f64,
+            Number::F64(n) => n,
+        }
+    }
+
+    /// The value as an `i64`, if it is an integer that fits.
+    pub fn as_i64(self) -> Option<i64> {
+        match self {
+            Number::U64(n) => i64::try_from(n).ok(),
+            Number::I64(n) => Some(n),
+            Number::F64(_) => None,
+        }
+    }
+
+    /// Whether the two are the same number, whatever their representation.
+    pub fn same_as(self, other: Number) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+
+    /// Orders the two by value. Integers are compared exactly, also with a
+    /// float and past 2^53 where `as_f64` rounds. `None` if one is NaN.
+    pub fn compare(self, other: Number) -> Option<Ordering> {
+        match (self.as_i128(), other.as_i128()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            (Some(a), None) => compare_int_float(a, other.as_f64()),
+            (None, Some(b)) => compare_int_float(b, self.as_f64()).map(Ordering::reverse),
+            (None, None) => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+
+    fn as_i128(self) -> Option<i128> {
+        match self {
+            Number::U64(n) => Some(n as i128),
+            Number::I64(n) => Some(n as i128),
+            Number::F64(_) => None,
+        }
+    }
+}
+
+fn compare_int_float(i: i128, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    // Past ±2^127 no integer here comes close.
+    if f >= 2f64.powi(127) {
+        return Some(Ordering::Less);
+    }
+    if f < -(2f64.powi(127)) {
+        return Some(Ordering::Greater);
+    }
+    let whole = f.trunc();
+    // `whole` is an integer in range, so the cast is exact.
+    Some(i.cmp(&(whole as i128)).then(whole.partial_cmp(&f)?))
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::U64(n) => n.fmt(f),
+            Number::I64(n) => n.fmt(f),
+            Number::F64(n) => n.fmt(f),
+        }
+    }
+}
+
+impl Value {
+    /// The value `pointer` (RFC 6901) refers to.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut value = self;
+        for token in pointer::parse(pointer).ok()? {
+            value = match value {
+                Value::Object(map) => map.get(&token)?,
+                Value::Array(items) => items.get(pointer::array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut value = self;
+        for token in pointer::parse(pointer).ok()? {
+            value = match value {
+                Value::Object(map) => map.get_mut(&token)?,
+                Value::Array(items) => items.get_mut(pointer::array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Whether the two are the same JSON value: like `==`, except that
+    /// numbers compare by value, so `1` and `1.0` are the same.
+    pub fn same_as(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.same_as(*b),
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_as(b))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.get(k).is_some_and(|w| v.same_as(w)))
+            }
+            _ => self == other,
+        }
+    }
+
+    /// The JSON name of the value's type, as used by JSON Schema.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(Number::F64(_)) => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => n.serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(items) => items.serialize(serializer),
+            Value::Object(map) => map.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match *self {
+            Number::U64(n) => serializer.serialize_u64(n),
+            Number::I64(n) => serializer.serialize_i64(n),
+            Number::F64(n) => serializer.serialize_f64(n),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        // Keep non-negative integers as `U64` whichever way they arrive, so
+        // equal values compare equal.
+        match u64::try_from(v) {
+            Ok(v) => Ok(Value::Number(Number::U64(v))),
+            Err(_) => Ok(Value::Number(Number::I64(v))),
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(Number::U64(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(Number::F64(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut members = Map::new();
+        while let Some((key, value)) = map.next_entry()? {
+            members.insert(key, value);
+        }
+        Ok(Value::Object(members))
+    }
+}
+
+#[test]
+fn test_value() {
+    use crate::de::from_str;
+    use crate::ser::to_string;
+
+    let s = r#"{"a": [1, -2, 2.5, "x", null, true], "b": {"c/d": {}}}"#;
+    let v: Value = from_str(s).unwrap();
+    assert_eq!(v.pointer("/a/1"), Some(&Value::Number(Number::I64(-2))));
+    assert_eq!(v.pointer("/a/3").and_then(Value::as_str), Some("x"));
+    assert_eq!(v.pointer("/b/c~1d"), Some(&Value::Object(Map::new())));
+    assert_eq!(v.pointer("/a/9"), None);
+    assert_eq!(
+        to_string(&v).unwrap(),
+        r#"{"a":[1,-2,2.5,"x",null,true],"b":{"c/d":{}}}"#
+    );
+    assert!(Number::U64(1).same_as(Number::F64(1.0)));
+    assert!(!Number::I64(-1).same_as(Number::U64(u64::MAX)));
+    // 2^53 + 1 has no `f64` of its own.
+    let big = Number::U64(9007199254740993);
+    assert!(!big.same_as(Number::U64(9007199254740992)));
+    assert!(!big.same_as(Number::F64(9007199254740992.0)));
+    assert_eq!(
+        big.compare(Number::F64(9007199254740992.0)),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Number::I64(-1).compare(Number::F64(-0.5)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Number::F64(-1.5).compare(Number::I64(-1)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Number::U64(u64::MAX).compare(Number::F64(1e300)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(Number::U64(0).compare(Number::F64(f64::NAN)), None);
+}